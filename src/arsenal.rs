@@ -0,0 +1,97 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::config::config;
+use crate::savant::{get_leaderboard_rows, LeaderboardRow};
+
+pub struct Pitch {
+    pub name: String,
+    pub usage: Option<f64>,
+    pub velocity: Option<f64>,
+    pub spin: Option<f64>,
+    // inches of induced vertical and horizontal break, from the catcher's view
+    pub vertical_break: Option<f64>,
+    pub horizontal_break: Option<f64>,
+    pub whiff: Option<f64>,
+    pub run_value: Option<f64>,
+}
+
+pub struct Arsenal {
+    pub name: String,
+    pub year: u16,
+    pub pitches: Vec<Pitch>,
+}
+
+fn number(row: Option<&LeaderboardRow>, column: &str) -> Option<f64> {
+    row?.get(column)?.trim().parse().ok()
+}
+
+// savant's csvs name players "Last, First"
+fn display_name(row: &LeaderboardRow) -> Option<String> {
+    let name = row.get("last_name, first_name")?;
+    Some(match name.split_once(", ") {
+        Some((last, first)) => format!("{first} {last}"),
+        None => name.clone(),
+    })
+}
+
+pub fn get_arsenal(savant_player_id: usize, year: u16) -> Result<Option<Arsenal>> {
+    let stats = get_leaderboard_rows(&format!("{}/leaderboard/pitch-arsenal-stats?type=pitcher&pitchType=&year={year}&team=&min=1&csv=true", config().savant_url), "player_id", savant_player_id)?;
+    let Some(name) = stats.first().and_then(display_name) else { return Ok(None) };
+    let spins = get_leaderboard_rows(&format!("{}/leaderboard/pitch-arsenals?year={year}&min=1&type=avg_spin&hand=&csv=true", config().savant_url), "pitcher", savant_player_id)?;
+    let mut pitches = Vec::new();
+    for row in &stats {
+        let Some(pitch_type) = row.get("pitch_type") else { continue };
+        // movement is only published one pitch type at a time
+        let movement = get_leaderboard_rows(&format!("{}/leaderboard/pitch-movement?year={year}&team=&min=1&pitch_type={pitch_type}&hand=&x=pitcher_break_x&z=pitcher_break_z_induced&csv=true", config().savant_url), "pitcher_id", savant_player_id)?;
+        pitches.push(Pitch {
+            name: row.get("pitch_name").cloned().unwrap_or_else(|| pitch_type.clone()),
+            usage: number(Some(row), "pitch_usage"),
+            velocity: number(movement.first(), "avg_speed"),
+            spin: number(spins.first(), &format!("{}_avg_spin", pitch_type.to_ascii_lowercase())),
+            vertical_break: number(movement.first(), "pitcher_break_z_induced"),
+            horizontal_break: number(movement.first(), "pitcher_break_x"),
+            whiff: number(Some(row), "whiff_percent"),
+            run_value: number(Some(row), "run_value"),
+        });
+    }
+    pitches.sort_by(|a, b| b.usage.unwrap_or_default().total_cmp(&a.usage.unwrap_or_default()));
+    Ok(Some(Arsenal { name, year, pitches }))
+}
+
+pub fn format_arsenal(arsenal: &Arsenal) -> Result<String> {
+    let cell = |value: Option<f64>, precision: usize| value.map(|value| format!("{value:.precision$}")).unwrap_or_else(|| "-".to_owned());
+    let mut table = String::new();
+    writeln!(table, "```")?;
+    writeln!(table, "{: <15} {: >5} {: >5} {: >5} {: >5} {: >5} {: >6} {: >4}", "Pitch", "Use%", "Velo", "Spin", "IVB", "HB", "Whiff%", "RV")?;
+    for pitch in &arsenal.pitches {
+        let name = pitch.name.chars().take(15).collect::<String>();
+        writeln!(table, "{name: <15} {: >5} {: >5} {: >5} {: >5} {: >5} {: >6} {: >4}", cell(pitch.usage, 1), cell(pitch.velocity, 1), cell(pitch.spin, 0), cell(pitch.vertical_break, 1), cell(pitch.horizontal_break, 1), cell(pitch.whiff, 1), cell(pitch.run_value, 0))?;
+    }
+    write!(table, "```")?;
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arsenal_table_lines_up_and_marks_missing_values() {
+        let pitch = |name: &str, usage: f64, spin: Option<f64>| Pitch { name: name.to_owned(), usage: Some(usage), velocity: Some(97.24), spin, vertical_break: Some(16.8), horizontal_break: Some(-8.1), whiff: Some(27.4), run_value: Some(-12.0) };
+        let arsenal = Arsenal { name: "Spencer Strider".to_owned(), year: 2023, pitches: vec![pitch("4-Seam Fastball", 56.4, Some(2391.0)), pitch("Slider", 37.9, None)] };
+        let table = format_arsenal(&arsenal).unwrap();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], "Pitch            Use%  Velo  Spin   IVB    HB Whiff%   RV");
+        assert_eq!(lines[2], "4-Seam Fastball  56.4  97.2  2391  16.8  -8.1   27.4  -12");
+        assert_eq!(lines[3], "Slider           37.9  97.2     -  16.8  -8.1   27.4  -12");
+        assert!(lines.iter().skip(1).take(3).all(|line| line.len() == lines[1].len()));
+    }
+
+    #[test]
+    fn leaderboard_names_are_flipped() {
+        let row = LeaderboardRow::from([("last_name, first_name".to_owned(), "Acuña Jr., Ronald".to_owned())]);
+        assert_eq!(display_name(&row).as_deref(), Some("Ronald Acuña Jr."));
+    }
+}
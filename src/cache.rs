@@ -0,0 +1,314 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use tracing::warn;
+
+use crate::config::config;
+use crate::http::{get, Upstream};
+use crate::metrics::metrics;
+
+// seconds
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+static CACHE: OnceLock<Cache> = OnceLock::new();
+
+pub struct Endpoint {
+    pub name: &'static str,
+    upstream: Upstream,
+    path: &'static str,
+    // `None` follows the configured live poll interval
+    ttl: Option<Duration>,
+}
+
+impl Endpoint {
+    fn ttl(&self) -> Duration {
+        self.ttl.unwrap_or_else(|| config().live_poll_interval())
+    }
+}
+
+// anything that isn't listed here is always fetched
+pub const ENDPOINTS: &[Endpoint] = &[
+    Endpoint { name: "live", upstream: Upstream::Savant, path: "/gf?", ttl: None },
+    // dates and matchups rarely change, callers that need the latest results ask for a refresh
+    Endpoint { name: "schedule", upstream: Upstream::StatsApi, path: "/api/v1/schedule", ttl: Some(Duration::from_secs(3 * HOUR)) },
+    Endpoint { name: "standings", upstream: Upstream::StatsApi, path: "/api/v1/standings", ttl: Some(Duration::from_secs(5 * MINUTE)) },
+    // the player directory keeps its own copy for a day, so these only save a second download on a refresh
+    Endpoint { name: "teams", upstream: Upstream::StatsApi, path: "/api/v1/teams", ttl: Some(Duration::from_secs(HOUR)) },
+    Endpoint { name: "players", upstream: Upstream::StatsApi, path: "/api/v1/sports/1/players", ttl: Some(Duration::from_secs(HOUR)) },
+    Endpoint { name: "percentiles", upstream: Upstream::Savant, path: "/savant-player/", ttl: Some(Duration::from_secs(DAY)) },
+    Endpoint { name: "leaderboards", upstream: Upstream::Savant, path: "/leaderboard/", ttl: Some(Duration::from_secs(DAY)) },
+    Endpoint { name: "search", upstream: Upstream::Savant, path: "/player/search-all", ttl: Some(Duration::from_secs(DAY)) },
+    Endpoint { name: "headshots", upstream: Upstream::Content, path: "/images/headshots/", ttl: Some(Duration::from_secs(7 * DAY)) },
+];
+
+fn endpoint(url: &str) -> Option<&'static Endpoint> {
+    let upstream = Upstream::of(url);
+    ENDPOINTS.iter().find(|endpoint| endpoint.upstream == upstream && url.contains(endpoint.path))
+}
+
+struct Entry {
+    endpoint: &'static Endpoint,
+    body: Arc<Vec<u8>>,
+    fetched_at: SystemTime,
+}
+
+impl Entry {
+    fn fresh(&self) -> bool {
+        self.fetched_at.elapsed().is_ok_and(|age| age < self.endpoint.ttl())
+    }
+}
+
+pub struct Cache {
+    entries: Mutex<HashMap<String, Entry>>,
+    // one lock per url being fetched, so identical requests wait for the first instead of making their own
+    in_flight: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    directory: Option<PathBuf>,
+}
+
+pub fn cache() -> &'static Cache {
+    CACHE.get_or_init(|| Cache::new(config().cache_directory()))
+}
+
+impl Cache {
+    fn new(directory: Option<PathBuf>) -> Cache {
+        if let Some(Err(e)) = directory.as_ref().map(std::fs::create_dir_all) {
+            warn!(error = %e, "Could not create the cache directory, responses are only cached in memory");
+            return Cache { entries: Mutex::default(), in_flight: Mutex::default(), directory: None }
+        }
+        let cache = Cache { entries: Mutex::default(), in_flight: Mutex::default(), directory };
+        cache.sweep();
+        cache
+    }
+
+    pub fn get(&self, url: &str) -> Result<Arc<Vec<u8>>> {
+        self.get_with(url, get)
+    }
+
+    // skips the cached copy for decisions that can't be made on an old one, what comes back is still cached for everyone else
+    pub fn refresh(&self, url: &str) -> Result<Arc<Vec<u8>>> {
+        self.refresh_with(url, get)
+    }
+
+    fn refresh_with(&self, url: &str, fetch: impl FnOnce(&str) -> Result<Vec<u8>>) -> Result<Arc<Vec<u8>>> {
+        let body = Arc::new(fetch(url)?);
+        if let Some(endpoint) = endpoint(url) {
+            self.store(url, endpoint, body.clone());
+        }
+        Ok(body)
+    }
+
+    fn get_with(&self, url: &str, fetch: impl FnOnce(&str) -> Result<Vec<u8>>) -> Result<Arc<Vec<u8>>> {
+        let Some(endpoint) = endpoint(url) else { return Ok(Arc::new(fetch(url)?)) };
+        if let Some(body) = self.lookup(url, endpoint) {
+            metrics().record_cache(endpoint.name, true);
+            return Ok(body)
+        }
+        let flight = self.in_flight.lock().entry(url.to_owned()).or_default().clone();
+        let _flight = flight.lock();
+        // whoever waited on the lock finds what the first request stored
+        if let Some(body) = self.lookup(url, endpoint) {
+            metrics().record_cache(endpoint.name, true);
+            return Ok(body)
+        }
+        metrics().record_cache(endpoint.name, false);
+        let result = fetch(url).map(Arc::new);
+        if let Ok(body) = &result {
+            self.store(url, endpoint, body.clone());
+        }
+        self.in_flight.lock().remove(url);
+        result
+    }
+
+    fn lookup(&self, url: &str, endpoint: &'static Endpoint) -> Option<Arc<Vec<u8>>> {
+        if let Some(entry) = self.entries.lock().get(url).filter(|entry| entry.fresh()) {
+            return Some(entry.body.clone())
+        }
+        let path = self.path(url)?;
+        let (stored_url, body, fetched_at) = read_file(&path)?;
+        let entry = Entry { endpoint, body: Arc::new(body), fetched_at };
+        if stored_url != url {
+            return None
+        }
+        if !entry.fresh() {
+            let _ = std::fs::remove_file(&path);
+            return None
+        }
+        let body = entry.body.clone();
+        self.entries.lock().insert(url.to_owned(), entry);
+        Some(body)
+    }
+
+    fn store(&self, url: &str, endpoint: &'static Endpoint, body: Arc<Vec<u8>>) {
+        if let Some(path) = self.path(url) {
+            let mut contents = format!("{url}\n").into_bytes();
+            contents.extend_from_slice(&body);
+            if let Err(e) = std::fs::write(&path, contents) {
+                warn!(error = %e, path = %path.display(), "Could not write a cached response");
+            }
+        }
+        let mut entries = self.entries.lock();
+        // expired entries are dropped as new ones come in, so old leaderboards don't pile up in memory or on disk
+        entries.retain(|url, entry| {
+            let fresh = entry.fresh();
+            if let Some(path) = self.path(url).filter(|_| !fresh) {
+                let _ = std::fs::remove_file(path);
+            }
+            fresh
+        });
+        entries.insert(url.to_owned(), Entry { endpoint, body, fetched_at: SystemTime::now() });
+    }
+
+    fn path(&self, url: &str) -> Option<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        Some(self.directory.as_ref()?.join(format!("{:016x}", hasher.finish())))
+    }
+
+    // deletes files left behind by earlier runs that have expired since, or that no endpoint would use anymore
+    fn sweep(&self) {
+        let Some(directory) = &self.directory else { return };
+        for path in std::fs::read_dir(directory).into_iter().flatten().flatten().map(|entry| entry.path()) {
+            let Some((url, _, fetched_at)) = read_file(&path) else { continue };
+            if endpoint(&url).is_none_or(|endpoint| fetched_at.elapsed().is_ok_and(|age| age >= endpoint.ttl())) {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+
+    // drops every cached response, or only one endpoint's, and returns how many urls were dropped
+    pub fn purge(&self, name: Option<&str>) -> usize {
+        let matches = |url: &str| name.is_none() || endpoint(url).map(|endpoint| endpoint.name) == name;
+        let mut purged = HashSet::new();
+        self.entries.lock().retain(|url, _| {
+            let purge = matches(url);
+            if purge {
+                purged.insert(url.clone());
+            }
+            !purge
+        });
+        if let Some(directory) = &self.directory {
+            for path in std::fs::read_dir(directory).into_iter().flatten().flatten().map(|entry| entry.path()) {
+                let Some((url, _, _)) = read_file(&path) else { continue };
+                if matches(&url) && std::fs::remove_file(&path).is_ok() {
+                    purged.insert(url);
+                }
+            }
+        }
+        purged.len()
+    }
+}
+
+// a cached response on disk is its url on the first line, then the body, and was fetched when it was written
+fn read_file(path: &Path) -> Option<(String, Vec<u8>, SystemTime)> {
+    let fetched_at = std::fs::metadata(path).ok()?.modified().ok()?;
+    let mut contents = std::fs::read(path).ok()?;
+    let newline = contents.iter().position(|byte| *byte == b'\n')?;
+    let body = contents.split_off(newline + 1);
+    contents.pop();
+    Some((String::from_utf8(contents).ok()?, body, fetched_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use anyhow::bail;
+
+    use super::*;
+
+    fn standings_url() -> String {
+        format!("{}/api/v1/standings?leagueId=104", config().statsapi_url)
+    }
+
+    #[test]
+    fn endpoints_are_matched_by_upstream_and_path() {
+        let name = |url: String| endpoint(&url).map(|endpoint| endpoint.name);
+        assert_eq!(name(format!("{}/gf?game_pk=745804", config().savant_url)), Some("live"));
+        assert_eq!(name(format!("{}/api/v1/schedule?sportId=1", config().statsapi_url)), Some("schedule"));
+        assert_eq!(name(format!("{}/savant-player/660670?stats=statcast-r-hitting-mlb", config().savant_url)), Some("percentiles"));
+        assert_eq!(name(format!("{}/api/v1/gf?game_pk=745804", config().statsapi_url)), None);
+        assert_eq!(name("https://example.com/api/v1/standings".to_owned()), None);
+    }
+
+    #[test]
+    fn responses_are_reused_until_purged() {
+        let cache = Cache::new(None);
+        let fetches = AtomicUsize::new(0);
+        let fetch = |_: &str| {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(b"{}".to_vec())
+        };
+        cache.get_with(&standings_url(), fetch).unwrap();
+        cache.get_with(&standings_url(), fetch).unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        assert_eq!(cache.purge(Some("schedule")), 0);
+        assert_eq!(cache.purge(Some("standings")), 1);
+        cache.get_with(&standings_url(), fetch).unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        // failures and endpoints that aren't listed are never kept
+        cache.purge(None);
+        assert!(cache.get_with(&standings_url(), |_| bail!("503")).is_err());
+        cache.get_with("https://example.com", fetch).unwrap();
+        cache.get_with("https://example.com", fetch).unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn identical_requests_share_one_fetch() {
+        let cache = Cache::new(None);
+        let fetches = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| cache.get_with(&standings_url(), |_| {
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(200));
+                    Ok(b"{}".to_vec())
+                }).unwrap());
+            }
+        });
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn responses_survive_a_restart_on_disk() {
+        let directory = std::env::temp_dir().join(format!("bunt-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        Cache::new(Some(directory.clone())).get_with(&standings_url(), |_| Ok(b"{\"records\": []}".to_vec())).unwrap();
+
+        let cache = Cache::new(Some(directory.clone()));
+        assert_eq!(cache.get_with(&standings_url(), |_| bail!("not cached")).unwrap().as_slice(), b"{\"records\": []}");
+        assert_eq!(cache.purge(None), 1);
+        assert!(std::fs::read_dir(&directory).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn expired_files_are_deleted() {
+        let directory = std::env::temp_dir().join(format!("bunt-cache-expired-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let cache = Cache::new(Some(directory.clone()));
+        cache.get_with(&standings_url(), |_| Ok(b"{}".to_vec())).unwrap();
+        let path = cache.path(&standings_url()).unwrap();
+        std::fs::write(directory.join("unknown"), "https://example.com\n{}").unwrap();
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(SystemTime::now() - Duration::from_secs(DAY)).unwrap();
+
+        Cache::new(Some(directory.clone()));
+        assert!(std::fs::read_dir(&directory).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn refreshes_skip_the_cached_copy_but_replace_it() {
+        let cache = Cache::new(None);
+        cache.get_with(&standings_url(), |_| Ok(b"old".to_vec())).unwrap();
+        assert_eq!(cache.refresh_with(&standings_url(), |_| Ok(b"new".to_vec())).unwrap().as_slice(), b"new");
+        assert_eq!(cache.get_with(&standings_url(), |_| bail!("not cached")).unwrap().as_slice(), b"new");
+    }
+}
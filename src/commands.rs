@@ -0,0 +1,421 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::{bail, Result};
+
+pub enum Kind {
+    // whatever words are left over, for player and team names
+    Text,
+    Integer,
+    Year,
+    // set by one of its words anywhere in the arguments
+    Flag(&'static [&'static str]),
+    // each word selects the value it's paired with
+    Choice(&'static [(&'static str, &'static str)]),
+}
+
+pub struct Param {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub required: bool,
+    pub description: &'static str,
+}
+
+impl Param {
+    pub const fn required(name: &'static str, kind: Kind, description: &'static str) -> Param {
+        Param { name, kind, required: true, description }
+    }
+
+    pub const fn optional(name: &'static str, kind: Kind, description: &'static str) -> Param {
+        Param { name, kind, required: false, description }
+    }
+
+    fn positional(&self) -> bool {
+        matches!(self.kind, Kind::Text | Kind::Integer | Kind::Year)
+    }
+
+    // a bare word that sets this parameter without naming it
+    fn keyword(&self, token: &str) -> Option<Argument> {
+        match self.kind {
+            Kind::Flag(words) => words.iter().any(|word| word.eq_ignore_ascii_case(token)).then_some(Argument::Flag),
+            Kind::Choice(choices) => choices.iter().find(|(word, _)| word.eq_ignore_ascii_case(token)).map(|(_, value)| Argument::Choice(value)),
+            _ => None,
+        }
+    }
+
+    fn parse(&self, token: &str) -> Result<Argument> {
+        Ok(match self.kind {
+            Kind::Text => Argument::Text(vec![token.to_owned()]),
+            Kind::Integer => match token.parse::<i64>() {
+                Ok(number) => Argument::Integer(number),
+                Err(_) => bail!("{} must be a number, not `{token}`", self.name),
+            },
+            Kind::Year => match token.parse::<i64>() {
+                Ok(year) if (1900..=2100).contains(&year) => Argument::Integer(year),
+                _ => bail!("{} must be a season like 2024, not `{token}`", self.name),
+            },
+            Kind::Flag(_) => bail!("{} doesn't take a value", self.name),
+            Kind::Choice(choices) => match self.keyword(token) {
+                Some(argument) => argument,
+                None => bail!("{} must be one of {}, not `{token}`", self.name, choices.iter().map(|(word, _)| format!("`{word}`")).collect::<Vec<_>>().join(", ")),
+            },
+        })
+    }
+
+    pub fn usage(&self) -> String {
+        let usage = match self.kind {
+            Kind::Flag(words) => words[0].to_owned(),
+            Kind::Choice(choices) => choices.iter().fold(Vec::<&str>::new(), |mut values, (_, value)| {
+                if !values.contains(value) {
+                    values.push(value);
+                }
+                values
+            }).join("|"),
+            _ => self.name.to_owned(),
+        };
+        if self.required { format!("<{usage}>") } else { format!("[{usage}]") }
+    }
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub params: &'static [Param],
+    pub description: &'static str,
+    // arguments and all, without the prefix
+    pub examples: &'static [&'static str],
+}
+
+const GAME: Param = Param::optional("game", Kind::Integer, "a game id, the active or most recent game of the configured team by default");
+const YEAR: Param = Param::optional("year", Kind::Year, "a season, the latest one by default");
+const LEAGUE: Param = Param::optional("league", Kind::Choice(&[("al", "al"), ("a", "al"), ("american", "al"), ("nl", "nl"), ("n", "nl"), ("national", "nl")]), "the league, the configured team's by default");
+const DIVISION: Param = Param::optional("division", Kind::Choice(&[("east", "east"), ("e", "east"), ("central", "central"), ("c", "central"), ("west", "west"), ("w", "west")]), "the division, the configured team's by default");
+const WILD_CARD: Param = Param::optional("wc", Kind::Flag(&["wc", "wildcard"]), "the whole league's wild card race instead of one division");
+
+// the order here is the order of the help embed
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "ev",
+        aliases: &[],
+        params: &[],
+        description: "Gets the statcast data from the most recent ball put in play in the configured team's active game",
+        examples: &["ev"],
+    },
+    Command {
+        name: "pitch",
+        aliases: &[],
+        params: &[Param::optional("zone", Kind::Flag(&["zone"]), "plots every pitch of the at bat")],
+        description: "Gets the pitch type, velocity, spin, break, location and call of the most recent pitch in the configured team's active game",
+        examples: &["pitch", "pitch zone"],
+    },
+    Command {
+        name: "standings",
+        aliases: &["st", "wc", "wildcard"],
+        params: &[LEAGUE, DIVISION, WILD_CARD],
+        description: "Gets the standings of the configured team's division or any other one, or a league's wild card race (`wc` and `wildcard` go straight to it)",
+        examples: &["st", "st al west", "wc al"],
+    },
+    Command {
+        name: "odds",
+        aliases: &[],
+        params: &[LEAGUE, DIVISION, WILD_CARD],
+        description: "Simulates the rest of the season to get the division, bye and playoff odds of every team in a division or league",
+        examples: &["odds", "odds al central", "odds wc"],
+    },
+    Command {
+        name: "magic",
+        aliases: &[],
+        params: &[Param::required("teams", Kind::Text, "a team, then optionally a rival"), Param::optional("wc", Kind::Flag(&["wc", "wildcard"]), "counts against the wild card field instead of a rival")],
+        description: "Gets the magic and tragic numbers of a team over a rival, their closest division rival, or the wild card",
+        examples: &["magic braves phillies", "magic braves wc", "magic \"red sox\" yankees"],
+    },
+    Command {
+        name: "wp",
+        aliases: &[],
+        params: &[GAME],
+        description: "Charts the home team's win probability over a game and marks its biggest swings",
+        examples: &["wp", "wp 745804"],
+    },
+    Command {
+        name: "mvp",
+        aliases: &["topplays"],
+        params: &[GAME],
+        description: "Ranks the biggest plays of a game and names the player of the game by win probability added",
+        examples: &["mvp", "topplays 745804"],
+    },
+    Command {
+        name: "savant",
+        aliases: &["sav"],
+        params: &[
+            Param::required("player", Kind::Text, "a name, nickname or savant id"),
+            YEAR,
+            Param::optional("career", Kind::Flag(&["career"]), "shows the trend over every season instead"),
+            Param::optional("card", Kind::Flag(&["card"]), "renders the season as an image"),
+        ],
+        description: "Gets the baseball savant percentile rankings of the most likely matching player",
+        examples: &["sav acuna", "sav strider 2023 card", "savant \"will smith\" career"],
+    },
+    Command {
+        name: "compare",
+        aliases: &[],
+        params: &[Param::required("players", Kind::Text, "two players, split by `vs` or each in quotes")],
+        description: "Compares the baseball savant percentile rankings of two hitters or two pitchers",
+        examples: &["compare acuna vs ohtani", "compare \"will smith\" \"max fried\""],
+    },
+    Command {
+        name: "arsenal",
+        aliases: &[],
+        params: &[Param::required("pitcher", Kind::Text, "a name, nickname or savant id"), YEAR],
+        description: "Gets a pitcher's usage, velocity, spin, movement, whiff rate and run value for every pitch they throw",
+        examples: &["arsenal strider", "arsenal fried 2023"],
+    },
+    Command {
+        name: "alias",
+        aliases: &[],
+        params: &[Param::optional("change", Kind::Text, "`<nickname> = <player>` to add one or `remove <nickname>`")],
+        description: "Lists this server's player nicknames, or lets server managers add and remove them",
+        examples: &["alias", "alias ronnie = acuna", "alias remove ronnie"],
+    },
+    Command {
+        name: "prefix",
+        aliases: &[],
+        params: &[Param::optional("prefix", Kind::Text, "the new prefix, or `reset` for the default")],
+        description: "Shows this server's prefix, or lets server managers change it, mentioning the bot always works too",
+        examples: &["prefix", "prefix !", "prefix reset"],
+    },
+    Command {
+        name: "purge",
+        aliases: &[],
+        params: &[Param::optional("endpoint", Kind::Text, "only that endpoint's responses, like `standings` or `percentiles`")],
+        description: "Lets the bot owner throw away cached responses so the next command fetches fresh ones",
+        examples: &["purge", "purge standings"],
+    },
+    Command {
+        name: "help",
+        aliases: &["h"],
+        params: &[Param::optional("command", Kind::Text, "a command to explain")],
+        description: "Lists every command, or explains one with its arguments and examples",
+        examples: &["help", "help savant"],
+    },
+];
+
+// the command word has to match a name or alias exactly, so `~stats` isn't `~st`
+pub fn find(word: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name.eq_ignore_ascii_case(word) || command.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(word)))
+}
+
+// splits on whitespace, keeping anything in quotes together, an unclosed quote runs to the end
+pub fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in input.chars() {
+        match c {
+            // phones like to curl quotes
+            '"' | '“' | '”' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    tokens.push(std::mem::take(&mut token));
+                    started = false;
+                }
+            }
+            c => {
+                token.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        tokens.push(token);
+    }
+    tokens
+}
+
+pub enum Argument {
+    Text(Vec<String>),
+    Integer(i64),
+    Flag,
+    Choice(&'static str),
+}
+
+#[derive(Default)]
+pub struct Arguments(HashMap<&'static str, Argument>);
+
+impl Arguments {
+    fn set(&mut self, param: &Param, argument: Argument) -> Result<()> {
+        if self.0.insert(param.name, argument).is_some() {
+            bail!("{} was given more than once", param.name);
+        }
+        Ok(())
+    }
+
+    pub fn text(&self, name: &str) -> Option<String> {
+        Some(self.words(name)?.join(" "))
+    }
+
+    // the words of a text argument, with quoted names kept whole
+    pub fn words(&self, name: &str) -> Option<&[String]> {
+        match self.0.get(name)? {
+            Argument::Text(words) => Some(words),
+            _ => None,
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.0.get(name)? {
+            Argument::Integer(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        matches!(self.0.get(name), Some(Argument::Flag))
+    }
+
+    pub fn choice(&self, name: &str) -> Option<&'static str> {
+        match self.0.get(name)? {
+            Argument::Choice(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Command {
+    // the name first, then the aliases
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(self.name).chain(self.aliases.iter().copied())
+    }
+
+    pub fn usage(&self) -> String {
+        self.params.iter().fold(self.name.to_owned(), |usage, param| usage + " " + &param.usage())
+    }
+
+    // flags and choices are picked out wherever they are and `name=value` sets anything by name, what's left fills the
+    // positional parameters in order, with the ones after the text recognized from the end
+    pub fn parse(&self, tokens: &[String]) -> Result<Arguments> {
+        let mut arguments = Arguments::default();
+        let mut positional = VecDeque::new();
+        for token in tokens {
+            let named = token.split_once('=').and_then(|(name, value)| Some((self.params.iter().find(|param| param.name.eq_ignore_ascii_case(name))?, value)));
+            if let Some((param, value)) = named {
+                arguments.set(param, param.parse(value)?)?;
+            } else if let Some((param, argument)) = self.params.iter().find_map(|param| Some((param, param.keyword(token)?))) {
+                arguments.set(param, argument)?;
+            } else {
+                positional.push_back(token.clone());
+            }
+        }
+
+        let slots = self.params.iter().filter(|param| param.positional() && !arguments.0.contains_key(param.name)).collect::<Vec<_>>();
+        let text = slots.iter().position(|param| matches!(param.kind, Kind::Text));
+        let (leading, trailing) = match text {
+            Some(idx) => (&slots[..idx], &slots[idx + 1..]),
+            None => (&slots[..], &[][..]),
+        };
+        for param in leading {
+            let Some(token) = positional.pop_front() else { break };
+            arguments.set(param, param.parse(&token)?)?;
+        }
+        // a required text keeps at least one word, so `~sav 2023` looks for a player rather than a season
+        let keep = text.is_some_and(|idx| slots[idx].required) as usize;
+        for param in trailing.iter().rev() {
+            if positional.len() <= keep {
+                break
+            }
+            if let Some(Ok(argument)) = positional.back().map(|token| param.parse(token)) {
+                positional.pop_back();
+                arguments.set(param, argument)?;
+            }
+        }
+        match text {
+            Some(idx) => if !positional.is_empty() {
+                arguments.set(slots[idx], Argument::Text(positional.into()))?;
+            },
+            None => if let Some(token) = positional.front() {
+                bail!("`{token}` isn't an argument of {}", self.name);
+            },
+        }
+
+        if let Some(param) = self.params.iter().find(|param| param.required && !arguments.0.contains_key(param.name)) {
+            bail!("{} is missing", param.name);
+        }
+        Ok(arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<Arguments> {
+        let tokens = tokenize(input);
+        find(&tokens[0]).unwrap().parse(&tokens[1..])
+    }
+
+    #[test]
+    fn quoted_words_stay_together() {
+        assert_eq!(tokenize("  compare \"Will Smith\"  vs “Max Fried” "), ["compare", "Will Smith", "vs", "Max Fried"]);
+        assert_eq!(tokenize("sav player=\"will smith\" \"\""), ["sav", "player=will smith", ""]);
+        assert_eq!(tokenize("magic \"red sox"), ["magic", "red sox"]);
+    }
+
+    #[test]
+    fn only_whole_command_words_match() {
+        for word in ["stats", "steal", "standingsfoo", "every", "s", ""] {
+            assert!(find(word).is_none(), "{word}");
+        }
+        assert_eq!(find("st").unwrap().name, "standings");
+        assert_eq!(find("WildCard").unwrap().name, "standings");
+        assert_eq!(find("topplays").unwrap().name, "mvp");
+        assert_eq!(find("h").unwrap().name, "help");
+    }
+
+    #[test]
+    fn arguments_are_typed() {
+        let standings = parse("st AL west wc").unwrap();
+        assert_eq!((standings.choice("league"), standings.choice("division"), standings.flag("wc")), (Some("al"), Some("west"), true));
+        assert_eq!(parse("st").unwrap().choice("league"), None);
+
+        let savant = parse("sav ronald acuna 2023 card").unwrap();
+        assert_eq!((savant.text("player").as_deref(), savant.integer("year"), savant.flag("card"), savant.flag("career")), (Some("ronald acuna"), Some(2023), true, false));
+        // a player id isn't mistaken for a season, and neither is a lone number
+        assert_eq!(parse("sav 660670").unwrap().text("player").as_deref(), Some("660670"));
+        assert_eq!(parse("sav 2023").unwrap().integer("year"), None);
+        assert_eq!(parse("sav year=2021 player=\"Will Smith\"").unwrap().text("player").as_deref(), Some("Will Smith"));
+
+        assert_eq!(parse("wp 745804").unwrap().integer("game"), Some(745804));
+        assert_eq!(parse("magic \"red sox\" yankees").unwrap().words("teams").unwrap(), ["red sox", "yankees"]);
+    }
+
+    #[test]
+    fn bad_input_is_explained() {
+        let error = |input: &str| parse(input).err().map(|e| e.to_string());
+        assert_eq!(error("st al nl").as_deref(), Some("league was given more than once"));
+        assert_eq!(error("st a lot").as_deref(), Some("`lot` isn't an argument of standings"));
+        assert_eq!(error("wp tomorrow").as_deref(), Some("game must be a number, not `tomorrow`"));
+        assert_eq!(error("arsenal").as_deref(), Some("pitcher is missing"));
+        assert_eq!(error("arsenal strider year=1492").as_deref(), Some("year must be a season like 2024, not `1492`"));
+        assert_eq!(error("odds league=mlb").as_deref(), Some("league must be one of `al`, `a`, `american`, `nl`, `n`, `national`, not `mlb`"));
+        assert_eq!(find("savant").unwrap().usage(), "savant <player> [year] [career] [card]");
+        assert_eq!(find("odds").unwrap().usage(), "odds [al|nl] [east|central|west] [wc]");
+    }
+
+    #[test]
+    fn every_command_is_documented_with_working_examples() {
+        for command in COMMANDS {
+            assert!(!command.description.is_empty() && !command.examples.is_empty(), "{}", command.name);
+            assert!(command.params.iter().all(|param| !param.description.is_empty()), "{}", command.name);
+            for example in command.examples {
+                let tokens = tokenize(example);
+                assert_eq!(find(&tokens[0]).map(|found| found.name), Some(command.name), "{example}");
+                assert!(command.parse(&tokens[1..]).is_ok(), "{example}");
+            }
+        }
+        // an alias can't shadow another command
+        let names = COMMANDS.iter().flat_map(Command::names).collect::<Vec<_>>();
+        assert!(names.iter().all(|name| names.iter().filter(|other| *other == name).count() == 1));
+    }
+}
@@ -0,0 +1,283 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+pub const DEFAULT_CONFIG_PATH: &str = "bunt.toml";
+pub const MAX_PREFIX_LENGTH: usize = 8;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub prefix: String,
+    pub team_id: i64,
+    pub storage_path: PathBuf,
+    pub player_directory_path: PathBuf,
+    // how long to wait before retrying a request that failed, and how many tries it gets before the command fails
+    pub retry_delay_ms: u64,
+    pub retry_attempts: u64,
+    pub http_timeout_secs: u64,
+    pub directory_refresh_hours: u64,
+    // how often the live game feed is fetched again, commands in between share the last response
+    pub live_poll_secs: u64,
+    pub statsapi_url: String,
+    pub savant_url: String,
+    pub content_url: String,
+    pub log_level: String,
+    pub log_format: LogFormat,
+    pub metrics_address: String,
+    pub cache_directory: String,
+    // only ever passed as a flag or through the environment, so it can't end up in a shared config file
+    #[serde(skip)]
+    pub token: String,
+}
+
+#[derive(Deserialize, ValueEnum, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            prefix: "~".to_owned(),
+            team_id: 144,
+            storage_path: PathBuf::from(crate::storage::STORAGE_PATH),
+            player_directory_path: PathBuf::from(crate::players::PLAYER_DIRECTORY_PATH),
+            retry_delay_ms: 1500,
+            retry_attempts: 3,
+            http_timeout_secs: 30,
+            directory_refresh_hours: 24,
+            live_poll_secs: 10,
+            statsapi_url: "https://statsapi.mlb.com".to_owned(),
+            savant_url: "https://baseballsavant.mlb.com".to_owned(),
+            content_url: "https://content.mlb.com".to_owned(),
+            log_level: "info".to_owned(),
+            log_format: LogFormat::Text,
+            metrics_address: "127.0.0.1:9898".to_owned(),
+            cache_directory: String::new(),
+            token: String::new(),
+        }
+    }
+}
+
+// every flag can also be set through its environment variable, and both win over the config file
+#[derive(Parser, Debug, Default)]
+#[command(version, about = "A discord bot for a team's games and baseball savant stats")]
+pub struct Args {
+    #[arg(long, env = "BUNT_CONFIG", help = "TOML config file, bunt.toml is read if it exists")]
+    pub config: Option<PathBuf>,
+    #[arg(long, env = "BUNT_DISCORD_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+    #[arg(long, value_name = "PLAYER_ID", help = "Save a player's savant pages into fixtures/ and exit")]
+    pub capture_fixture: Option<usize>,
+    #[arg(long, env = "BUNT_PREFIX")]
+    pub prefix: Option<String>,
+    #[arg(long, env = "BUNT_TEAM_ID")]
+    pub team_id: Option<i64>,
+    #[arg(long, env = "BUNT_STORAGE_PATH")]
+    pub storage_path: Option<PathBuf>,
+    #[arg(long, env = "BUNT_PLAYER_DIRECTORY_PATH")]
+    pub player_directory_path: Option<PathBuf>,
+    #[arg(long, env = "BUNT_RETRY_DELAY_MS")]
+    pub retry_delay_ms: Option<u64>,
+    #[arg(long, env = "BUNT_RETRY_ATTEMPTS")]
+    pub retry_attempts: Option<u64>,
+    #[arg(long, env = "BUNT_HTTP_TIMEOUT_SECS")]
+    pub http_timeout_secs: Option<u64>,
+    #[arg(long, env = "BUNT_DIRECTORY_REFRESH_HOURS")]
+    pub directory_refresh_hours: Option<u64>,
+    #[arg(long, env = "BUNT_LIVE_POLL_SECS")]
+    pub live_poll_secs: Option<u64>,
+    #[arg(long, env = "BUNT_STATSAPI_URL")]
+    pub statsapi_url: Option<String>,
+    #[arg(long, env = "BUNT_SAVANT_URL")]
+    pub savant_url: Option<String>,
+    #[arg(long, env = "BUNT_CONTENT_URL")]
+    pub content_url: Option<String>,
+    #[arg(long, env = "BUNT_LOG_LEVEL")]
+    pub log_level: Option<String>,
+    #[arg(long, env = "BUNT_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    #[arg(long, env = "BUNT_METRICS_ADDRESS")]
+    pub metrics_address: Option<String>,
+    #[arg(long, env = "BUNT_CACHE_DIRECTORY")]
+    pub cache_directory: Option<String>,
+}
+
+impl Config {
+    pub fn load(args: &Args) -> Result<Config> {
+        let path = args.config.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str::<Config>(&contents).with_context(|| format!("Could not parse {}", path.display()))?,
+            // only a config file that was asked for has to exist
+            Err(_) if args.config.is_none() => Config::default(),
+            Err(e) => bail!("Could not read {}: {e}", path.display()),
+        };
+        config.apply(args);
+        config.validate()?;
+        // capturing fixtures never connects to discord
+        if args.capture_fixture.is_none() {
+            config.validate_token()?;
+        }
+        Ok(config)
+    }
+
+    fn apply(&mut self, args: &Args) {
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+
+        set(&mut self.prefix, &args.prefix);
+        set(&mut self.team_id, &args.team_id);
+        set(&mut self.storage_path, &args.storage_path);
+        set(&mut self.player_directory_path, &args.player_directory_path);
+        set(&mut self.retry_delay_ms, &args.retry_delay_ms);
+        set(&mut self.retry_attempts, &args.retry_attempts);
+        set(&mut self.http_timeout_secs, &args.http_timeout_secs);
+        set(&mut self.directory_refresh_hours, &args.directory_refresh_hours);
+        set(&mut self.live_poll_secs, &args.live_poll_secs);
+        set(&mut self.statsapi_url, &args.statsapi_url);
+        set(&mut self.savant_url, &args.savant_url);
+        set(&mut self.content_url, &args.content_url);
+        set(&mut self.log_level, &args.log_level);
+        set(&mut self.log_format, &args.log_format);
+        set(&mut self.metrics_address, &args.metrics_address);
+        set(&mut self.cache_directory, &args.cache_directory);
+        set(&mut self.token, &args.token);
+    }
+
+    fn validate(&mut self) -> Result<()> {
+        validate_prefix(&self.prefix)?;
+        if self.team_id <= 0 {
+            bail!("team_id must be an MLB team id");
+        }
+        for (name, value) in [("retry_delay_ms", self.retry_delay_ms), ("retry_attempts", self.retry_attempts), ("http_timeout_secs", self.http_timeout_secs), ("directory_refresh_hours", self.directory_refresh_hours), ("live_poll_secs", self.live_poll_secs)] {
+            if value == 0 {
+                bail!("{name} must be greater than zero");
+            }
+        }
+        for (name, url) in [("statsapi_url", &mut self.statsapi_url), ("savant_url", &mut self.savant_url), ("content_url", &mut self.content_url)] {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                bail!("{name} must be an http or https url, not {url:?}");
+            }
+            *url = url.trim_end_matches('/').to_owned();
+        }
+        if let Err(e) = EnvFilter::try_new(&self.log_level) {
+            bail!("log_level must be a log filter like \"info\" or \"info,bunt=debug\": {e}");
+        }
+        if !self.metrics_address.is_empty() && self.metrics_address.parse::<SocketAddr>().is_err() {
+            bail!("metrics_address must be an address like \"127.0.0.1:9898\" or empty, not {:?}", self.metrics_address);
+        }
+        Ok(())
+    }
+
+    fn validate_token(&self) -> Result<()> {
+        if self.token.is_empty() {
+            bail!("a discord token is required, pass it with --token or BUNT_DISCORD_TOKEN");
+        }
+        // bot tokens are three dot separated parts
+        if self.token.split('.').count() != 3 || self.token.chars().any(char::is_whitespace) {
+            bail!("the discord token doesn't look like a bot token");
+        }
+        Ok(())
+    }
+
+    pub fn metrics_address(&self) -> Option<SocketAddr> {
+        self.metrics_address.parse().ok()
+    }
+
+    pub fn cache_directory(&self) -> Option<PathBuf> {
+        (!self.cache_directory.is_empty()).then(|| PathBuf::from(&self.cache_directory))
+    }
+
+    pub fn retry_delay(&self) -> Duration {
+        Duration::from_millis(self.retry_delay_ms)
+    }
+
+    pub fn live_poll_interval(&self) -> Duration {
+        Duration::from_secs(self.live_poll_secs)
+    }
+}
+
+pub fn validate_prefix(prefix: &str) -> Result<()> {
+    if prefix.is_empty() || prefix.chars().any(char::is_whitespace) || prefix.chars().count() > MAX_PREFIX_LENGTH {
+        bail!("prefix must be 1 to {MAX_PREFIX_LENGTH} characters without whitespace");
+    }
+    Ok(())
+}
+
+pub fn init(config: Config) {
+    CONFIG.set(config).expect("Config was already initialized");
+}
+
+// the defaults stand in when nothing was loaded, which is how tests run
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "MTA.Gx.abc";
+
+    // an explicit empty config file, so a bunt.toml in the working directory can't change what the tests see
+    fn args() -> Args {
+        let path = std::env::temp_dir().join(format!("bunt-config-empty-{}.toml", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        Args { config: Some(path), token: Some(TOKEN.to_owned()), ..Args::default() }
+    }
+
+    #[test]
+    fn flags_override_the_file_and_urls_are_normalized() {
+        let path = std::env::temp_dir().join(format!("bunt-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "prefix = \"!\"\nteam_id = 121\nsavant_url = \"http://localhost:8080/\"\n").unwrap();
+        let config = Config::load(&Args { config: Some(path), team_id: Some(143), player_directory_path: Some(PathBuf::from("data/players.json")), ..args() }).unwrap();
+        assert_eq!(config.prefix, "!");
+        assert_eq!(config.team_id, 143);
+        assert_eq!(config.player_directory_path, PathBuf::from("data/players.json"));
+        assert_eq!(config.savant_url, "http://localhost:8080");
+        assert_eq!(config.statsapi_url, Config::default().statsapi_url);
+        assert_eq!(config.token, TOKEN);
+    }
+
+    #[test]
+    fn invalid_configs_are_refused() {
+        assert!(Config::load(&Args { config: Some(PathBuf::from("/nonexistent/bunt.toml")), ..args() }).is_err());
+        assert!(Config::load(&Args { prefix: Some("! ".to_owned()), ..args() }).is_err());
+        assert!(Config::load(&Args { http_timeout_secs: Some(0), ..args() }).is_err());
+        assert!(Config::load(&Args { live_poll_secs: Some(0), ..args() }).is_err());
+        assert!(Config::load(&Args { statsapi_url: Some("statsapi.mlb.com".to_owned()), ..args() }).is_err());
+        assert!(Config::load(&Args { log_level: Some("info,bunt=loud".to_owned()), ..args() }).is_err());
+        assert!(Config::load(&Args { metrics_address: Some("localhost".to_owned()), ..args() }).is_err());
+        assert_eq!(Config::load(&Args { metrics_address: Some(String::new()), ..args() }).unwrap().metrics_address(), None);
+
+        let path = std::env::temp_dir().join(format!("bunt-config-unknown-{}.toml", std::process::id()));
+        std::fs::write(&path, "prefx = \"!\"\n").unwrap();
+        assert!(Config::load(&Args { config: Some(path), ..args() }).is_err());
+    }
+
+    #[test]
+    fn the_token_is_required_to_connect() {
+        assert!(Config::load(&Args { token: None, ..args() }).is_err());
+        assert!(Config::load(&Args { token: Some("not a token".to_owned()), ..args() }).is_err());
+        assert!(Config::load(&Args { token: None, capture_fixture: Some(660670), ..args() }).is_ok());
+    }
+
+    #[test]
+    fn the_example_config_matches_the_defaults() {
+        assert_eq!(toml::from_str::<Config>(include_str!("../bunt.example.toml")).unwrap(), Config::default());
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+use crate::http::Upstream;
+
+// failures a command can run into that aren't bugs, returned through anyhow and picked back out by `categorize`
+#[derive(Debug)]
+pub enum CommandError {
+    NoGame,
+    PlayerNotFound(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::NoGame => write!(f, "No game was found"),
+            CommandError::PlayerNotFound(search) => write!(f, "No player matched {search:?}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+// a request that failed or came back unreadable, only `http::get` and the json decoding of its responses make these
+#[derive(Debug)]
+pub struct UpstreamError {
+    pub url: String,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl UpstreamError {
+    pub fn new(url: &str, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> UpstreamError {
+        UpstreamError { url: url.to_owned(), source: source.into() }
+    }
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Could not get {}", self.url)
+    }
+}
+
+impl std::error::Error for UpstreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Category {
+    NoGame,
+    PlayerNotFound(String),
+    // the name of the service that failed
+    Upstream(&'static str),
+    Internal,
+}
+
+impl Category {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Category::NoGame => "No Game",
+            Category::PlayerNotFound(_) => "Player Not Found",
+            Category::Upstream(_) => "Stats Unavailable",
+            Category::Internal => "Something Went Wrong",
+        }
+    }
+
+    // for the outcome label of the command metrics
+    pub fn label(&self) -> &'static str {
+        match self {
+            Category::NoGame => "no_game",
+            Category::PlayerNotFound(_) => "player_not_found",
+            Category::Upstream(_) => "upstream",
+            Category::Internal => "internal",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Category::NoGame => "There's no game to show right now, try again once one has started or pass a game id".to_owned(),
+            Category::PlayerNotFound(search) => format!("No player ID or name matched `{search}`"),
+            Category::Upstream(service) => format!("{service} couldn't be reached or sent something unexpected, try again in a few minutes"),
+            Category::Internal => "That command ran into a bug, it's been logged".to_owned(),
+        }
+    }
+}
+
+fn service(url: &str) -> &'static str {
+    match Upstream::of(url) {
+        Upstream::Savant => "Baseball Savant",
+        Upstream::StatsApi => "The MLB stats API",
+        Upstream::Content => "MLB's image server",
+        Upstream::Other => "An upstream service",
+    }
+}
+
+pub fn categorize(error: &anyhow::Error) -> Category {
+    if let Some(error) = error.downcast_ref::<CommandError>() {
+        return match error {
+            CommandError::NoGame => Category::NoGame,
+            CommandError::PlayerNotFound(search) => Category::PlayerNotFound(search.clone()),
+        }
+    }
+    // anything else, like a storage write that failed, is on our side
+    match error.chain().find_map(|cause| cause.downcast_ref::<UpstreamError>()) {
+        Some(upstream) => Category::Upstream(service(&upstream.url)),
+        None => Category::Internal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+
+    use super::*;
+    use crate::config::config;
+
+    #[test]
+    fn errors_are_categorized_through_their_context() {
+        let no_game = Err::<(), _>(CommandError::NoGame).context("Could not get today's game").unwrap_err();
+        assert_eq!(categorize(&no_game), Category::NoGame);
+        assert_eq!(categorize(&anyhow!(CommandError::PlayerNotFound("acuna".to_owned()))), Category::PlayerNotFound("acuna".to_owned()));
+
+        let standings_url = format!("{}/api/v1/standings", config().statsapi_url);
+        let unavailable = ureq::Error::Status(503, ureq::Response::new(503, "Service Unavailable", "").unwrap());
+        assert_eq!(categorize(&anyhow!(UpstreamError::new(&standings_url, unavailable)).context("Could not get standings")), Category::Upstream("The MLB stats API"));
+        let truncated = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "truncated");
+        assert_eq!(categorize(&anyhow!(UpstreamError::new(&standings_url, truncated))), Category::Upstream("The MLB stats API"));
+
+        // a local write failing isn't an outage
+        let read_only = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "read-only file system");
+        assert_eq!(categorize(&anyhow!(read_only).context("Could not write bunt.json.tmp")), Category::Internal);
+
+        assert_eq!(categorize(&anyhow!("Could not get home team name")), Category::Internal);
+    }
+
+    #[test]
+    fn services_are_named_by_their_configured_url() {
+        assert_eq!(service(&format!("{}/gf?game_pk=745804", config().savant_url)), "Baseball Savant");
+        assert_eq!(service(&format!("{}/api/v1/standings", config().statsapi_url)), "The MLB stats API");
+        assert_eq!(service("https://example.com"), "An upstream service");
+    }
+}
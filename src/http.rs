@@ -0,0 +1,69 @@
+use std::io::Read;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tracing::{debug, warn};
+
+use crate::config::config;
+use crate::error::UpstreamError;
+use crate::metrics::metrics;
+
+static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Upstream {
+    Savant,
+    StatsApi,
+    Content,
+    Other,
+}
+
+impl Upstream {
+    pub fn of(url: &str) -> Upstream {
+        let config = config();
+        if url.starts_with(&config.savant_url) {
+            Upstream::Savant
+        } else if url.starts_with(&config.statsapi_url) {
+            Upstream::StatsApi
+        } else if url.starts_with(&config.content_url) {
+            Upstream::Content
+        } else {
+            Upstream::Other
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Upstream::Savant => "savant",
+            Upstream::StatsApi => "statsapi",
+            Upstream::Content => "content",
+            Upstream::Other => "other",
+        }
+    }
+}
+
+fn http() -> &'static ureq::Agent {
+    AGENT.get_or_init(|| ureq::AgentBuilder::new().timeout(Duration::from_secs(config().http_timeout_secs)).build())
+}
+
+// every upstream request goes through here so its status and latency end up in the logs of the command that made it,
+// commands fetch through the cache instead
+pub fn get(url: &str) -> Result<Vec<u8>> {
+    let started = Instant::now();
+    let response = http().get(url).call();
+    let latency_ms = started.elapsed().as_millis() as u64;
+    metrics().record_upstream(Upstream::of(url), response.is_ok());
+    match &response {
+        Ok(response) => debug!(url, status = response.status(), latency_ms, "Upstream request"),
+        Err(ureq::Error::Status(status, _)) => warn!(url, status, latency_ms, "Upstream request failed"),
+        Err(e) => warn!(url, latency_ms, error = %e, "Upstream request failed"),
+    }
+    let mut body = Vec::new();
+    response.map_err(|e| UpstreamError::new(url, e))?.into_reader().read_to_end(&mut body).map_err(|e| UpstreamError::new(url, e))?;
+    Ok(body)
+}
+
+pub fn headshot_url(savant_player_id: usize) -> String {
+    format!("{}/images/headshots/current/60x60/{savant_player_id}@3x.png", config().content_url)
+}
@@ -0,0 +1,11 @@
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{Config, LogFormat};
+
+pub fn init_logging(config: &Config) {
+    let subscriber = tracing_subscriber::fmt().with_env_filter(EnvFilter::new(&config.log_level));
+    match config.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().flatten_event(true).with_current_span(true).init(),
+    }
+}
@@ -0,0 +1,137 @@
+pub const SEASON_GAMES: u32 = 162;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+}
+
+// the head-to-head season series from the perspective of one team, `games` counts every scheduled meeting
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SeasonSeries {
+    pub wins: u32,
+    pub losses: u32,
+    pub games: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tiebreaker {
+    Held,
+    Lost,
+    Undecided,
+}
+
+impl SeasonSeries {
+    pub fn reversed(self) -> SeasonSeries {
+        SeasonSeries { wins: self.losses, losses: self.wins, games: self.games }
+    }
+
+    // an even split falls through to intradivision record, which we don't model, so it is never counted as held
+    pub fn tiebreaker(self) -> Tiebreaker {
+        if self.wins * 2 > self.games {
+            Tiebreaker::Held
+        } else if self.losses * 2 > self.games {
+            Tiebreaker::Lost
+        } else {
+            Tiebreaker::Undecided
+        }
+    }
+}
+
+// number of `team` wins or `rival` losses until `rival` can no longer finish ahead of `team`, zero once clinched
+pub fn magic_number(team: Record, rival: Record, series: SeasonSeries) -> u32 {
+    let magic_number = SEASON_GAMES as i64 + 1 - team.wins as i64 - rival.losses as i64 - (series.tiebreaker() == Tiebreaker::Held) as i64;
+    magic_number.max(0) as u32
+}
+
+// number of `team` losses or `rival` wins until `team` can no longer finish ahead of `rival`, zero once eliminated
+pub fn tragic_number(team: Record, rival: Record, series: SeasonSeries) -> u32 {
+    magic_number(rival, team, series.reversed())
+}
+
+// clinching one of `spots` places means finishing ahead of every other contender but `spots - 1` of them
+pub fn wild_card_magic_number(team: Record, contenders: &[(Record, SeasonSeries)], spots: usize) -> u32 {
+    let mut magic_numbers = contenders.iter().map(|&(rival, series)| magic_number(team, rival, series)).collect::<Vec<_>>();
+    magic_numbers.sort_unstable_by(|a, b| b.cmp(a));
+    magic_numbers.get(spots - 1).copied().unwrap_or(0)
+}
+
+// elimination means `spots` other contenders have each clinched finishing ahead of `team`
+pub fn wild_card_tragic_number(team: Record, contenders: &[(Record, SeasonSeries)], spots: usize) -> Option<u32> {
+    let mut tragic_numbers = contenders.iter().map(|&(rival, series)| tragic_number(team, rival, series)).collect::<Vec<_>>();
+    tragic_numbers.sort_unstable();
+    tragic_numbers.get(spots - 1).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(wins: u32, losses: u32) -> Record {
+        Record { wins, losses }
+    }
+
+    fn series(wins: u32, losses: u32, games: u32) -> SeasonSeries {
+        SeasonSeries { wins, losses, games }
+    }
+
+    #[test]
+    fn tiebreaker_needs_a_series_majority() {
+        assert_eq!(series(7, 3, 13).tiebreaker(), Tiebreaker::Held);
+        assert_eq!(series(6, 3, 13).tiebreaker(), Tiebreaker::Undecided);
+        assert_eq!(series(3, 7, 13).tiebreaker(), Tiebreaker::Lost);
+        assert_eq!(series(3, 3, 6).tiebreaker(), Tiebreaker::Undecided);
+        assert_eq!(series(4, 1, 6).tiebreaker(), Tiebreaker::Held);
+    }
+
+    #[test]
+    fn mid_season_magic_and_tragic_numbers() {
+        let team = record(90, 60);
+        let rival = record(84, 66);
+        assert_eq!(magic_number(team, rival, series(6, 6, 13)), 7);
+        assert_eq!(magic_number(team, rival, series(7, 5, 13)), 6);
+        assert_eq!(tragic_number(rival, team, series(5, 7, 13)), 6);
+        assert_eq!(tragic_number(team, rival, series(6, 6, 13)), 19);
+    }
+
+    #[test]
+    fn braves_won_the_2022_nl_east_on_the_season_series() {
+        // both clubs finished 101-61, Atlanta took the season series 10-9
+        let braves = record(101, 61);
+        let mets = record(101, 61);
+        assert_eq!(magic_number(braves, mets, series(10, 9, 19)), 0);
+        assert_eq!(tragic_number(braves, mets, series(10, 9, 19)), 1);
+        assert_eq!(tragic_number(mets, braves, series(9, 10, 19)), 0);
+    }
+
+    #[test]
+    fn braves_clinched_the_2023_nl_east() {
+        let braves = record(104, 58);
+        let phillies = record(90, 72);
+        assert_eq!(magic_number(braves, phillies, series(8, 5, 13)), 0);
+        assert_eq!(tragic_number(braves, phillies, series(8, 5, 13)), 15);
+    }
+
+    #[test]
+    fn red_sox_hosted_the_2021_al_wild_card_game() {
+        // Boston and New York both finished 92-70, Boston won the season series 10-9
+        let red_sox = record(92, 70);
+        let yankees = record(92, 70);
+        let blue_jays = record(91, 71);
+        let mariners = record(90, 72);
+        let contenders = [(yankees, series(10, 9, 19)), (blue_jays, series(8, 11, 19)), (mariners, series(3, 4, 7))];
+        assert_eq!(wild_card_magic_number(red_sox, &contenders, 2), 0);
+        assert_eq!(magic_number(red_sox, yankees, series(10, 9, 19)), 0);
+    }
+
+    #[test]
+    fn wild_card_numbers_skip_the_contenders_who_can_still_pass() {
+        let team = record(85, 65);
+        let contenders = [(record(88, 62), series(3, 4, 7)), (record(86, 64), series(2, 4, 6)), (record(84, 66), series(4, 2, 6)), (record(80, 70), series(3, 3, 6))];
+        // with three spots, only the third and fourth contenders have to be kept behind
+        assert_eq!(wild_card_magic_number(team, &contenders, 3), 11);
+        assert_eq!(wild_card_magic_number(team, &contenders, 1), 16);
+        assert_eq!(wild_card_tragic_number(team, &contenders, 3), Some(14));
+        assert_eq!(wild_card_tragic_number(team, &contenders, 5), None);
+    }
+}
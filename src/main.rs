@@ -1,72 +1,219 @@
-#![feature(let_chains)]
-
-use std::env;
-use std::ops::Deref;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::str::FromStr;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context as AnyhowContext, Result};
 use chrono::{Datelike, DateTime, Local, Month, Utc};
+use clap::Parser;
 use parking_lot::RwLock;
-use scraper::{ElementRef, Html, Selector};
 use serde_json::Value;
-use serenity::all::{CreateEmbed, CreateMessage, Message};
+use serenity::all::{ChannelId, ComponentInteractionDataKind, CreateActionRow, CreateAttachment, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, GuildId, Message, Ready, ShardStageUpdateEvent, Typing};
 use serenity::async_trait;
+use serenity::gateway::ConnectionStage;
 use serenity::prelude::*;
-
-pub const ATLANTA_BRAVES_TEAM_ID: i64 = 144;
-pub const NL_LEAGUE_ID: i64 = 104;
-pub const NL_EAST_DIVISION_ID: i64 = 204;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::arsenal::{format_arsenal, get_arsenal};
+use crate::cache::{cache, ENDPOINTS};
+use crate::commands::{tokenize, Arguments, Command, COMMANDS};
+use crate::config::{config, validate_prefix, Args, Config};
+use crate::error::{categorize, Category, CommandError, UpstreamError};
+use crate::http::{headshot_url, Upstream};
+use crate::logging::init_logging;
+use crate::magic::Tiebreaker;
+use crate::metrics::metrics;
+use crate::odds::PlayoffOdds;
+use crate::pitch::get_game_pitches;
+use crate::players::{normalize_name, PlayerDirectory};
+use crate::render::{get_headshot, render_percentile_card, render_win_probability, render_zone_plot};
+use crate::savant::{fill_metric_values, format_percentile_comparison, format_percentile_ranking, format_percentile_trend, get_percentile_rankings, search_players, PercentileRankings, PlayerPercentiles, PlayerSearch, Section, SECTIONS};
+use crate::season::{get_home_division, get_remaining_games, get_season_games, get_season_series, get_team_records, standings_target, TeamRecord, AL_LEAGUE_ID};
+use crate::storage::Storage;
+use crate::wpa::{biggest_swings, get_game_wpa, player_wpa};
+
+mod arsenal;
+mod cache;
+mod commands;
+mod config;
+mod error;
+mod http;
+mod logging;
+mod magic;
+mod metrics;
+mod odds;
+mod pitch;
+mod players;
+mod render;
+mod savant;
+mod season;
+mod storage;
+mod wpa;
+
+pub const WILD_CARD_SPOTS: usize = 3;
+pub const FIXTURES_DIRECTORY: &str = "fixtures";
+pub const WIN_PROBABILITY_SWINGS: usize = 3;
+pub const TOP_PLAYS: usize = 5;
+pub const TOP_PLAYERS: usize = 3;
+// games checked per command while looking for the active one, so a schedule that lags behind savant can't keep it going
+pub const GAME_LOOKUPS: usize = 4;
 
 pub fn get_with_sleep(url: &str) -> Result<Value> {
+    retry(url, || cache().get(url))
+}
+
+// for decisions that can't be made on a cached copy
+pub fn get_fresh_with_sleep(url: &str) -> Result<Value> {
+    retry(url, || cache().refresh(url))
+}
+
+// gives up with the last error after `retry_attempts` tries, so an outage reaches the user instead of hanging the command,
+// it sleeps between tries so commands only call it through `blocking`
+fn retry(url: &str, fetch: impl Fn() -> Result<Arc<Vec<u8>>>) -> Result<Value> {
+    let mut attempt = 1;
     loop {
-        match ureq::get(url).call() {
-            Ok(response) => return Ok(response.into_json::<Value>()?),
-            Err(_) => std::thread::sleep(Duration::from_millis(1500))
+        match fetch() {
+            Ok(body) => return Ok(serde_json::from_slice::<Value>(&body).map_err(|e| UpstreamError::new(url, e))?),
+            Err(e) if attempt >= config().retry_attempts => return Err(e),
+            Err(_) => {
+                metrics().record_retry(Upstream::of(url));
+                std::thread::sleep(config().retry_delay());
+                attempt += 1;
+            }
         }
     }
 }
 
+// runs work that blocks, like fetching with retries or simulating seasons, off the async workers so other commands keep being answered meanwhile
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work).await.context("Could not finish a blocking task")?
+}
+
+// stops typing however a command ends, including an early return through `?`
+struct TypingIndicator(Option<Typing>);
+
+impl TypingIndicator {
+    fn start(ctx: &Context, channel_id: ChannelId) -> TypingIndicator {
+        TypingIndicator(Some(channel_id.start_typing(&ctx.http)))
+    }
+
+    fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for TypingIndicator {
+    fn drop(&mut self) {
+        if let Some(typing) = self.0.take() {
+            typing.stop();
+        }
+    }
+}
+
+// cheap to clone, so fetches that block can take a handle along into `blocking`
+#[derive(Clone)]
 struct Handler {
-    current_game_id: RwLock<Option<usize>>,
+    storage: Arc<RwLock<Storage>>,
+    player_directory: Arc<RwLock<PlayerDirectory>>,
 }
 
 impl Handler {
-    fn get_today_game(&self) -> Option<Value> {
-        let mut current_game_id = self.current_game_id.write();
+    // the stored game until savant has it final, then the next one on the schedule, `None` once there are none left
+    fn get_today_game(&self) -> Result<Option<Value>> {
+        // the storage lock is only taken around reads and writes, every command needs it for the prefix
+        let stored = self.storage.read().state().current_game_id;
+        // the schedule can still have a game live for a while after savant has it final
+        let mut finished = HashSet::new();
+        let mut game_pk = stored;
+        let mut game = None;
+        for _ in 0..GAME_LOOKUPS {
+            let next = match game_pk {
+                Some(game_pk) => game_pk,
+                None => match self.next_game(&finished)? {
+                    Some(next) => next,
+                    None => break,
+                },
+            };
+            let response = get_with_sleep(&format!("{}/gf?game_pk={next}", config().savant_url))?;
+            if response["scoreboard"]["status"]["abstractGameState"].as_str() == Some("Final") {
+                finished.insert(next);
+                game_pk = None;
+            } else {
+                game_pk = Some(next);
+                game = Some(response);
+                break
+            }
+        }
+        if game_pk != stored {
+            self.set_current_game(game_pk);
+        }
+        Ok(game)
+    }
 
-        let game_pk = if let Some(game_pk) = current_game_id.deref().clone() {
-            Some(game_pk)
-        } else {
-            let all_games_root = get_with_sleep(&format!("https://statsapi.mlb.com/api/v1/schedule/games/?sportId=1&startDate={year}-01-01&endDate={year}-12-31&hydrate=venue(timezone)", year = Local::now().date_naive().year())).ok()?;
-            let next_game = all_games_root["dates"]
-                .as_array()?
-                .iter()
-                .flat_map(|date| date["games"].as_array().expect("Date has games").iter())
-                .filter(|game| game["teams"]["home"]["team"]["id"].as_i64() == Some(ATLANTA_BRAVES_TEAM_ID) || game["teams"]["away"]["team"]["id"].as_i64() == Some(ATLANTA_BRAVES_TEAM_ID))
-                .filter(|game| game["status"]["abstractGameState"].as_str() != Some("Final"))
-                .map(|game| game["gamePk"].as_i64().expect("Game ID exists") as usize)
-                .next();
-            *current_game_id = next_game;
-            next_game
-        }?/*747123*/;
-
-        let response = get_with_sleep(&format!("https://baseballsavant.mlb.com/gf?game_pk={game_pk}")).ok()?;
-        if response["scoreboard"]["status"]["abstractGameState"].as_str() == Some("Final") {
-            *current_game_id = None;
-            self.get_today_game()
-        } else {
-            Some(response)
+    // always from a fresh schedule, a cached one could hand back a game that has finished since
+    fn next_game(&self, finished: &HashSet<usize>) -> Result<Option<usize>> {
+        let all_games_root = get_fresh_with_sleep(&format!("{}/api/v1/schedule/games/?sportId=1&startDate={year}-01-01&endDate={year}-12-31&hydrate=venue(timezone)", config().statsapi_url, year = Local::now().date_naive().year()))?;
+        Ok(all_games_root["dates"]
+            .as_array()
+            .context("Could not get schedule dates")?
+            .iter()
+            .flat_map(|date| date["games"].as_array().into_iter().flatten())
+            .filter(|game| game["teams"]["home"]["team"]["id"].as_i64() == Some(config().team_id) || game["teams"]["away"]["team"]["id"].as_i64() == Some(config().team_id))
+            .filter(|game| game["status"]["abstractGameState"].as_str() != Some("Final"))
+            .filter_map(|game| game["gamePk"].as_u64().map(|game_pk| game_pk as usize))
+            .find(|game_pk| !finished.contains(game_pk)))
+    }
+
+    fn set_current_game(&self, game_pk: Option<usize>) {
+        if let Err(e) = self.storage.write().update(|state| state.current_game_id = game_pk) {
+            warn!(error = format!("{e:#}"), "Could not save the current game");
         }
     }
 
-    pub async fn exit_velocity(&self, ctx: Context, msg: Message) -> Result<()> {
-        let typing_trigger = msg.channel_id.start_typing(&ctx.http);
-        let current_braves_game = self.get_today_game().context("Could not get today's game")?;
+    // a game_pk argument picks any game, otherwise the configured team's active game or, between games, the last one played
+    fn get_requested_game(&self, game_pk: Option<i64>) -> Result<Value> {
+        // savant answers unknown game ids with a feed that has no win probability
+        let has_wpa = |game: &Value| game["scoreboard"]["stats"]["wpa"]["gameWpa"].as_array().is_some_and(|wpa| !wpa.is_empty());
+        if let Some(game_pk) = game_pk {
+            let game = get_with_sleep(&format!("{}/gf?game_pk={game_pk}", config().savant_url))?;
+            return if has_wpa(&game) { Ok(game) } else { Err(CommandError::NoGame.into()) }
+        }
+        if let Some(game) = self.get_today_game()?.filter(has_wpa) {
+            return Ok(game)
+        }
+        // fresh, so a game that just ended is the last one played
+        let all_games_root = get_fresh_with_sleep(&format!("{}/api/v1/schedule/games/?sportId=1&startDate={year}-01-01&endDate={year}-12-31", config().statsapi_url, year = Local::now().date_naive().year()))?;
+        let game_pk = all_games_root["dates"]
+            .as_array()
+            .context("Could not get schedule dates")?
+            .iter()
+            .flat_map(|date| date["games"].as_array().into_iter().flatten())
+            .filter(|game| game["teams"]["home"]["team"]["id"].as_i64() == Some(config().team_id) || game["teams"]["away"]["team"]["id"].as_i64() == Some(config().team_id))
+            .filter(|game| game["status"]["abstractGameState"].as_str() == Some("Final"))
+            .filter_map(|game| game["gamePk"].as_u64())
+            .next_back()
+            .ok_or(CommandError::NoGame)?;
+        get_with_sleep(&format!("{}/gf?game_pk={game_pk}", config().savant_url))
+    }
+
+    async fn today_game(&self) -> Result<Option<Value>> {
+        let handler = self.clone();
+        blocking(move || handler.get_today_game()).await
+    }
+
+    async fn requested_game(&self, game_pk: Option<i64>) -> Result<Value> {
+        let handler = self.clone();
+        blocking(move || handler.get_requested_game(game_pk)).await
+    }
+
+    pub async fn exit_velocity(&self, ctx: Context, msg: Message, _: Arguments) -> Result<()> {
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let current_braves_game = self.today_game().await?.ok_or(CommandError::NoGame)?;
         if let Some(hit) = current_braves_game["exit_velocity"].as_array().and_then(|ev| ev.last()) {
             let home_name = current_braves_game["scoreboard"]["teams"]["home"]["name"].as_str().context("Could not get home team name")?;
             let away_name = current_braves_game["scoreboard"]["teams"]["away"]["name"].as_str().context("Could not get away team name")?;
-            let is_home = current_braves_game["scoreboard"]["teams"]["home"]["id"].as_i64().context("Could not get home team id")? == ATLANTA_BRAVES_TEAM_ID;
+            let is_home = current_braves_game["scoreboard"]["teams"]["home"]["id"].as_i64().context("Could not get home team id")? == config().team_id;
             let title = if is_home { format!("{home_name} vs. {away_name}") } else { format!("{home_name} @ {away_name}") };
             let full_desc = hit["des"].as_str().context("Could not get description")?;
             let ab_index = hit["ab_number"].as_u64().context("Could not get at bat number")? - 1;
@@ -91,26 +238,99 @@ impl Handler {
             }
             typing_trigger.stop();
             msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed)).await?;
+        } else {
+            typing_trigger.stop();
+            msg.channel_id.say(&ctx.http, "No balls have been put in play in the active game yet").await?;
         }
 
         Ok(())
     }
 
-    pub async fn standings(&self, ctx: Context, msg: Message) -> Result<()> {
-        use std::fmt::Write;
+    pub async fn pitch(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let current_braves_game = self.today_game().await?.ok_or(CommandError::NoGame)?;
+        let pitches = get_game_pitches(&current_braves_game);
+        let Some(pitch) = pitches.last() else {
+            typing_trigger.stop();
+            msg.channel_id.say(&ctx.http, "No pitches have been thrown in the active game yet").await?;
+            return Ok(())
+        };
+        let unknown = || "-".to_owned();
+        let break_inches = |inches: Option<f64>| inches.map(|inches| format!("{inches:.1} in")).unwrap_or_else(unknown);
+        let mut embed = CreateEmbed::new()
+            .title(format!("{} to {}", pitch.pitcher_name, pitch.batter_name))
+            .description(format!("{} ({}-{})", pitch.description, pitch.balls, pitch.strikes))
+            .field("Pitch", &pitch.pitch_name, true)
+            .field("Velocity", pitch.velocity.map(|velocity| format!("{velocity:.1}mph")).unwrap_or_else(unknown), true)
+            .field("Spin Rate", pitch.spin.map(|spin| format!("{spin:.0} rpm")).unwrap_or_else(unknown), true)
+            .field("Induced Vertical Break", break_inches(pitch.vertical_break), true)
+            .field("Horizontal Break", break_inches(pitch.horizontal_break), true)
+            .field("Location", pitch.location().unwrap_or_else(unknown), true);
+        let mut message = CreateMessage::new();
+        if arguments.flag("zone") {
+            let at_bat = pitches.iter().filter(|other| other.at_bat == pitch.at_bat).collect::<Vec<_>>();
+            embed = embed.image("attachment://zone.png");
+            message = message.add_file(CreateAttachment::bytes(render_zone_plot(&at_bat)?, "zone.png"));
+        }
+        typing_trigger.stop();
+        msg.channel_id.send_message(&ctx.http, message.embed(embed)).await?;
+        Ok(())
+    }
 
-        let typing_trigger = msg.channel_id.start_typing(&ctx.http);
-        let msg_words = msg.content.split_ascii_whitespace().collect::<Vec<_>>();
-        let (target_league_id, target_division_id, wild_card) = {
-            let american_league = msg_words.iter().any(|word| word.eq_ignore_ascii_case("al") || word.eq_ignore_ascii_case("a") || word.eq_ignore_ascii_case("american"));
-            let division = if msg_words.iter().any(|word| word.eq_ignore_ascii_case("west") || word.eq_ignore_ascii_case("w")) { 0 } else if msg_words.iter().any(|word| word.eq_ignore_ascii_case("central") || word.eq_ignore_ascii_case("c")) { 2 } else { 1 };
-            let wild_card = msg_words.iter().any(|word| word.eq_ignore_ascii_case("wc") || word.eq_ignore_ascii_case("wild card") || msg.content.starts_with("~wc") || msg.content.starts_with("~wildcard"));
+    pub async fn win_probability(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let game = self.requested_game(arguments.integer("game")).await?;
+        let plays = get_game_wpa(&game)?;
+        if plays.is_empty() {
+            typing_trigger.stop();
+            msg.channel_id.say(&ctx.http, "That game hasn't started yet").await?;
+            return Ok(())
+        }
+        let home_name = game["scoreboard"]["teams"]["home"]["name"].as_str().context("Could not get home team name")?;
+        let away_name = game["scoreboard"]["teams"]["away"]["name"].as_str().context("Could not get away team name")?;
+        let title = format!("{away_name} @ {home_name}");
+        let png = render_win_probability(&title, home_name, away_name, &plays, &biggest_swings(&plays, WIN_PROBABILITY_SWINGS))?;
+        typing_trigger.stop();
+        let embed = CreateEmbed::new().title(format!("{title} Win Probability")).image("attachment://win_probability.png");
+        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed).add_file(CreateAttachment::bytes(png, "win_probability.png"))).await?;
+        Ok(())
+    }
 
-            (if american_league { 103 } else { 104 }, 200 + division + (!american_league) as i64 * 3, wild_card)
+    pub async fn top_plays(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let game = self.requested_game(arguments.integer("game")).await?;
+        let plays = get_game_wpa(&game)?;
+        let players = player_wpa(&plays);
+        let Some(player_of_the_game) = players.first() else {
+            typing_trigger.stop();
+            msg.channel_id.say(&ctx.http, "That game hasn't started yet").await?;
+            return Ok(())
         };
-        let standings = get_with_sleep(&format!("https://statsapi.mlb.com/api/v1/standings?leagueId={target_league_id}&hydrate=team,division"))?;
+        let home_name = game["scoreboard"]["teams"]["home"]["name"].as_str().context("Could not get home team name")?;
+        let away_name = game["scoreboard"]["teams"]["away"]["name"].as_str().context("Could not get away team name")?;
+        let team_name = |home: bool| if home { home_name } else { away_name };
+        let top_plays = biggest_swings(&plays, TOP_PLAYS).into_iter().map(|idx| &plays[idx]).map(|play| format!("`+{:.1}%` {} - {}", play.home_added.abs(), team_name(play.home_added >= 0.0), play.summary())).collect::<Vec<_>>().join("\n");
+        let mut embed = CreateEmbed::new()
+            .title(format!("{away_name} @ {home_name} Top Plays"))
+            .description(format!("Player of the game: **{}** ({}, `{:+.1}%`)\n\n{top_plays}", player_of_the_game.name, team_name(player_of_the_game.home), player_of_the_game.wpa));
+        for home in [false, true] {
+            let leaders = players.iter().filter(|player| player.home == home).take(TOP_PLAYERS).map(|player| format!("`{:+.1}%` {}", player.wpa, player.name)).collect::<Vec<_>>().join("\n");
+            embed = embed.field(team_name(home), leaders, true);
+        }
+        typing_trigger.stop();
+        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed.footer(CreateEmbedFooter::new("Win probability added, batters and pitchers are credited with every plate appearance they're part of")))).await?;
+        Ok(())
+    }
+
+    pub async fn standings(&self, ctx: Context, msg: Message, arguments: Arguments, wild_card: bool) -> Result<()> {
+        use std::fmt::Write;
+
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let (target_league_id, target_division_id) = standings_target(arguments.choice("league"), arguments.choice("division"), blocking(get_home_division).await?);
+        let wild_card = wild_card || arguments.flag("wc");
+        let standings = blocking(move || get_with_sleep(&format!("{}/api/v1/standings?leagueId={target_league_id}&hydrate=team,division", config().statsapi_url))).await?;
         let division = if wild_card { None } else { Some(standings["records"].as_array().context("Could not get standings")?.iter().find(|division| division["division"]["id"].as_i64() == Some(target_division_id)).context("Could not find division")?) };
-        let division_name = if let Some(division) = division { division["division"]["nameShort"].as_str().context("Could not get division name")? } else { if target_league_id == 103 { "AL Wild Card" } else { "NL Wild Card" } };
+        let division_name = if let Some(division) = division { division["division"]["nameShort"].as_str().context("Could not get division name")? } else { if target_league_id == AL_LEAGUE_ID { "AL Wild Card" } else { "NL Wild Card" } };
         let selected_teams = if let Some(division) = division {
             division["teamRecords"].as_array().context("Could not get divisions teams")?.iter().collect::<Vec<_>>()
         } else {
@@ -129,7 +349,7 @@ impl Handler {
             let wpct = team["winningPercentage"].as_str().context("Could not get team's WPCT")?;
             let third_stat = if timestamp.month() >= Month::September.number_from_month() { team["magicNumber"].as_str().context("Could not get magic number")? } else { team[if wild_card { "wildCardGamesBack" } else { "gamesBack" }].as_str().context("Could not get games back")? };
             let fourth_stat = if timestamp.month() >= Month::September.number_from_month() { team["eliminationNumber"].as_str().context("Could not get elimination number")? } else { team["streak"]["streakCode"].as_str().context("Could not get streak")? };
-            table.push((if division_leader { "D".to_owned() } else if wc_rank <= 3 { wc_rank.to_string() } else { " ".to_owned() }, format!("{club_name}"), format!("{wpct}"), third_stat, fourth_stat));
+            table.push((if division_leader { "D".to_owned() } else if wc_rank <= 3 { wc_rank.to_string() } else { " ".to_owned() }, club_name.to_owned(), wpct.to_owned(), third_stat, fourth_stat));
         }
         let first_stat = "Team";
         let second_stat = "WPCT";
@@ -153,245 +373,629 @@ impl Handler {
         Ok(())
     }
 
-    pub async fn savant(&self, ctx: Context, msg: Message) -> Result<()> {
+    pub async fn odds(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
         use std::fmt::Write;
 
-        let savant_player_id = 'a: {
-            if let Some(rest) = msg.content.strip_prefix("~savant ").or(msg.content.strip_prefix("~sav ")) {
-                if let Some(id) = rest.parse::<usize>().ok() {
-                    break 'a id
-                } else {
-                    let search = get_with_sleep(&format!("https://baseballsavant.mlb.com/player/search-all?search={rest}"))?;
-                    if let Some(id) = search[0]["id"].as_str().and_then(|str| str.parse().ok()) {
-                        break 'a id
-                    }
-                }
-            }
-            msg.channel_id.say(&ctx.http, "No player ID or name matched the given argument").await?;
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let wild_card = arguments.flag("wc");
+        let (home, teams, remaining, odds) = blocking(move || {
+            let (home, teams, remaining) = (get_home_division()?, get_team_records()?, get_remaining_games()?);
+            let odds = odds::simulate(&teams, &remaining, odds::SIMULATIONS, &mut rand::thread_rng());
+            Ok((home, teams, remaining, odds))
+        }).await?;
+        let (target_league_id, target_division_id) = standings_target(arguments.choice("league"), arguments.choice("division"), home);
+        let mut selected_teams = teams.iter().zip(odds.iter()).filter(|(team, _)| if wild_card { team.league_id == target_league_id } else { team.division_id == target_division_id }).collect::<Vec<_>>();
+        selected_teams.sort_by(|(_, a), (_, b)| b.playoffs.total_cmp(&a.playoffs).then(b.division.total_cmp(&a.division)));
+        let division_name = if wild_card { if target_league_id == AL_LEAGUE_ID { "AL" } else { "NL" } } else { selected_teams.first().map(|(team, _)| team.division_name.as_str()).context("Could not find division")? };
+
+        fn format_odds(probability: f64) -> String {
+            if (0.999..1.0).contains(&probability) { ">99.9%".to_owned() } else if probability > 0.0 && probability < 0.001 { "<0.1%".to_owned() } else { format!("{:.1}%", probability * 100.0) }
+        }
+
+        let table = selected_teams.iter().map(|(team, PlayoffOdds { division, bye, playoffs })| (team.name.clone(), format!("{}-{}", team.wins, team.losses), format_odds(*division), format_odds(*bye), format_odds(*playoffs))).collect::<Vec<_>>();
+        let (first_stat, second_stat, third_stat, fourth_stat, fifth_stat) = ("Team", "W-L", "Div", "Bye", "Playoffs");
+        let widths = table.iter().fold((first_stat.len(), second_stat.len(), third_stat.len(), fourth_stat.len()), |(m1, m2, m3, m4), (a, b, c, d, _)| (m1.max(a.len()), m2.max(b.len()), m3.max(c.len()), m4.max(d.len())));
+        let mut description = String::new();
+        writeln!(description, "```")?;
+        writeln!(description, "{first_stat: <a_width$}  {second_stat: <b_width$}  {third_stat: >c_width$}  {fourth_stat: >d_width$}  {fifth_stat}", a_width = widths.0, b_width = widths.1, c_width = widths.2, d_width = widths.3)?;
+        for (a, b, c, d, e) in table {
+            writeln!(description, "{a: <a_width$}  {b: <b_width$}  {c: >c_width$}  {d: >d_width$}  {e: >fifth_width$}", a_width = widths.0, b_width = widths.1, c_width = widths.2, d_width = widths.3, fifth_width = fifth_stat.len())?;
+        }
+        write!(description, "```")?;
+        let embed = CreateEmbed::new().title(format!("{division_name} Playoff Odds")).description(description).footer(CreateEmbedFooter::new(format!("{} simulations of the {} remaining games", odds::SIMULATIONS, remaining.len())));
+
+        typing_trigger.stop();
+        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed)).await?;
+        Ok(())
+    }
+
+    pub async fn magic(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let (teams, games) = blocking(|| Ok((get_team_records()?, get_season_games()?))).await?;
+        let words = arguments.words("teams").unwrap_or_default();
+        let find_team = |words: &[String]| teams.iter().find(|team| team.matches(&words.join(" ")));
+        let wild_card = arguments.flag("wc");
+        let (team, rival) = if wild_card {
+            (find_team(words), None)
+        } else if let Some(team) = find_team(words) {
+            (Some(team), None)
+        } else {
+            (1..words.len()).find_map(|idx| Some((find_team(&words[..idx])?, find_team(&words[idx..])?))).map_or((None, None), |(team, rival)| (Some(team), Some(rival)))
+        };
+        let Some(team) = team else {
+            typing_trigger.stop();
+            msg.channel_id.say(&ctx.http, format!("No team matched, usage: `{}magic <team> [rival team | wc]`", self.prefix(msg.guild_id))).await?;
             return Ok(())
         };
+        let by_record = |a: &&TeamRecord, b: &&TeamRecord| (b.wins as i64 - b.losses as i64).cmp(&(a.wins as i64 - a.losses as i64));
 
-        let typing_trigger = msg.channel_id.start_typing(&ctx.http);
-
-        #[allow(non_snake_case)]
-        struct PercentileRankings {
-            year: u16,
-            name: String,
-            xwOBA: Option<u16>,
-            xBA: Option<u16>,
-            xSLG: Option<u16>,
-            AvgEV: Option<u16>,
-            BatSpeed: Option<u16>,
-            BarrelPct: Option<u16>,
-            HardHitPct: Option<u16>,
-            ChasePct: Option<u16>,
-            WhiffPct: Option<u16>,
-            KPct: Option<u16>,
-            BBPct: Option<u16>,
-            OAA: Option<u16>,
-            ArmStrength: Option<u16>,
-            Speed: Option<u16>,
-            xERA: Option<u16>,
-            FBVelo: Option<u16>,
-            FBSpin: Option<u16>,
-            CBSpin: Option<u16>,
-            Extension: Option<u16>,
-        }
-
-        impl PercentileRankings {
-            fn hitter(&self) -> bool {
-                self.xwOBA.is_some()
-            }
+        let embed = if wild_card {
+            let division_leaders = teams.iter().filter(|other| other.league_id == team.league_id).fold(Vec::<&TeamRecord>::new(), |mut leaders, other| {
+                match leaders.iter_mut().find(|leader| leader.division_id == other.division_id) {
+                    Some(leader) => if by_record(&other, leader).is_lt() { *leader = other },
+                    None => leaders.push(other),
+                }
+                leaders
+            });
+            let contenders = teams.iter().filter(|other| other.league_id == team.league_id && other.id != team.id && !division_leaders.iter().any(|leader| leader.id == other.id)).map(|other| (other.record(), get_season_series(&games, team.id, other.id))).collect::<Vec<_>>();
+            let magic_number = magic::wild_card_magic_number(team.record(), &contenders, WILD_CARD_SPOTS);
+            let tragic_number = magic::wild_card_tragic_number(team.record(), &contenders, WILD_CARD_SPOTS);
+            CreateEmbed::new()
+                .title(format!("{} Wild Card", team.name))
+                .field("Magic Number", if magic_number == 0 { "Clinched".to_owned() } else { magic_number.to_string() }, true)
+                .field("Tragic Number", match tragic_number { Some(0) => "Eliminated".to_owned(), Some(tragic_number) => tragic_number.to_string(), None => "-".to_owned() }, true)
+        } else {
+            let rival = match rival {
+                Some(rival) => rival,
+                None => {
+                    let mut division = teams.iter().filter(|other| other.division_id == team.division_id).collect::<Vec<_>>();
+                    division.sort_by(by_record);
+                    *division.iter().find(|other| other.id != team.id).context("Could not find a division rival")?
+                }
+            };
+            let series = get_season_series(&games, team.id, rival.id);
+            let magic_number = magic::magic_number(team.record(), rival.record(), series);
+            let tragic_number = magic::tragic_number(team.record(), rival.record(), series);
+            let tiebreaker = match series.tiebreaker() {
+                Tiebreaker::Held => format!("{} hold the tiebreaker", team.name),
+                Tiebreaker::Lost => format!("{} hold the tiebreaker", rival.name),
+                Tiebreaker::Undecided => "tiebreaker undecided".to_owned(),
+            };
+            CreateEmbed::new()
+                .title(format!("{} vs. {}", team.name, rival.name))
+                .field("Magic Number", if magic_number == 0 { "Clinched".to_owned() } else { magic_number.to_string() }, true)
+                .field("Tragic Number", if tragic_number == 0 { "Eliminated".to_owned() } else { tragic_number.to_string() }, true)
+                .field("Season Series", format!("{}-{} of {} ({tiebreaker})", series.wins, series.losses, series.games), true)
+        };
 
-            fn fielder(&self) -> bool {
-                self.OAA.is_some() | self.ArmStrength.is_some()
-            }
+        typing_trigger.stop();
+        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed)).await?;
+        Ok(())
+    }
 
-            fn runner(&self) -> bool {
-                self.Speed.is_some()
-            }
+    async fn resolve_player(&self, ctx: &Context, msg: &Message, search: &str) -> Result<Option<usize>> {
+        const MAX_SELECT_MENU_OPTIONS: usize = 25;
+
+        let aliases = self.aliases(msg.guild_id);
+        let handler = self.clone();
+        blocking(move || {
+            handler.refresh_player_directory();
+            Ok(())
+        }).await?;
+        let local_search = self.player_directory.read().search(aliases.as_ref(), search);
+        let remote_search = search.to_owned();
+        let found = match local_search {
+            Some(found) => found,
+            None => blocking(move || search_players(&remote_search)).await?,
+        };
+        let mut candidates = match found {
+            PlayerSearch::Found(id) => return Ok(Some(id)),
+            PlayerSearch::NotFound => return Err(CommandError::PlayerNotFound(search.to_owned()).into()),
+            PlayerSearch::Candidates(candidates) => candidates,
+        };
+        // savant's search isn't ordered, so the ones that don't fit in the menu are always the least recent
+        candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.last_year));
+        let options = candidates.iter().take(MAX_SELECT_MENU_OPTIONS).map(|candidate| {
+            let option = CreateSelectMenuOption::new(&candidate.name, candidate.id.to_string());
+            let summary = candidate.summary();
+            if summary.is_empty() { option } else { option.description(summary) }
+        }).collect();
+        let menu = CreateSelectMenu::new("player", CreateSelectMenuKind::String { options }).placeholder("Which player did you mean?");
+        let content = if candidates.len() > MAX_SELECT_MENU_OPTIONS { format!("{} players matched `{search}`, only the {MAX_SELECT_MENU_OPTIONS} most recent are shown so narrow the search if yours isn't there", candidates.len()) } else { format!("{} players matched `{search}`", candidates.len()) };
+        let prompt = msg.channel_id.send_message(&ctx.http, CreateMessage::new().content(content).components(vec![CreateActionRow::SelectMenu(menu)])).await?;
+        let Some(interaction) = prompt.await_component_interaction(&ctx.shard).author_id(msg.author.id).timeout(Duration::from_secs(60)).await else {
+            prompt.delete(&ctx.http).await?;
+            return Ok(None)
+        };
+        let ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind else { return Ok(None) };
+        let id = values.first().and_then(|value| value.parse::<usize>().ok());
+        let name = candidates.iter().find(|candidate| Some(candidate.id) == id).map_or(search, |candidate| &candidate.name);
+        interaction.create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new().content(format!("Looking up {name}")).components(Vec::new()))).await?;
+        Ok(id)
+    }
 
-            fn pitcher(&self) -> bool {
-                self.xERA.is_some()
-            }
-        }
+    // copied out so the storage lock is never held together with the directory's
+    fn aliases(&self, guild_id: Option<GuildId>) -> Option<HashMap<String, usize>> {
+        guild_id.and_then(|guild_id| Some(self.storage.read().state().guilds.get(&guild_id.get())?.aliases.clone()))
+    }
 
-        fn get_percentile_rankings(savant_player_id: usize) -> Result<Option<PercentileRankings>> {
-            fn get_percentile_from_element(row: &[ElementRef], ordinal: usize) -> Option<u16> {
-                row.get(ordinal)?.child_elements().next()?.inner_html().parse::<u16>().ok()
+    // downloads without holding the lock, lookups keep using the old directory meanwhile
+    fn refresh_player_directory(&self) {
+        if !self.player_directory.read().is_stale() {
+            return
+        }
+        match PlayerDirectory::download() {
+            Ok(player_directory) => *self.player_directory.write() = player_directory,
+            Err(e) => {
+                warn!(error = format!("{e:#}"), "Could not refresh the player directory");
+                self.player_directory.write().refresh_failed();
             }
+        }
+    }
 
-            let html = Html::parse_document(&ureq::get(&format!("https://baseballsavant.mlb.com/savant-player/{savant_player_id}?stats=statcast-r-hitting-mlb")).call()?.into_string()?);
-            let selector = Selector::parse("table[id=percentileRankings]").unwrap();
-            let Some(element) = html.select(&selector).next() else { return Ok(None) };
-            let row = element.child_elements().nth(1).unwrap().child_elements().last().unwrap().child_elements().collect::<Vec<_>>();
-            let name_selector = Selector::parse(r#"div[class="bio-player-name"]"#).unwrap();
-            let name = html.select(&name_selector).next().unwrap().child_elements().next().unwrap().inner_html();
-            let mut rankings = PercentileRankings {
-                year: row[0].child_elements().next().unwrap().inner_html().parse().unwrap(),
-                name,
-                xwOBA: None,
-                xBA: None,
-                xSLG: None,
-                AvgEV: None,
-                BatSpeed: None,
-                BarrelPct: None,
-                HardHitPct: None,
-                ChasePct: None,
-                WhiffPct: None,
-                KPct: None,
-                BBPct: None,
-                OAA: None,
-                ArmStrength: None,
-                Speed: None,
-                xERA: None,
-                FBVelo: None,
-                FBSpin: None,
-                CBSpin: None,
-                Extension: None,
-            };
-            for (idx, child) in element.child_elements().nth(0).unwrap().child_elements().next().unwrap().child_elements().enumerate() {
-                let name = child.inner_html().split_ascii_whitespace().collect::<Vec<_>>().join(" ");
-                let value = get_percentile_from_element(&row, idx);
-                *match &*name {
-                    "Year" => continue,
-                    "xwOBA" => &mut rankings.xwOBA,
-                    "xBA" => &mut rankings.xBA,
-                    "xSLG" => &mut rankings.xSLG,
-                    "xISO" => continue,
-                    "xOBP" => continue,
-                    "Brl" => continue,
-                    "Brl%" => &mut rankings.BarrelPct,
-                    "EV" => &mut rankings.AvgEV,
-                    "Max EV" => continue,
-                    "Hard <br>Hit%" => &mut rankings.HardHitPct,
-                    "K%" => &mut rankings.KPct,
-                    "BB%" => &mut rankings.BBPct,
-                    "Whiff%" => &mut rankings.WhiffPct,
-                    "Chase <br>Rate" => &mut rankings.ChasePct,
-                    "Speed" => &mut rankings.Speed,
-                    "OAA" => &mut rankings.OAA,
-                    "Arm <br> Strength" => &mut rankings.ArmStrength,
-                    "Bat <br> Speed" => &mut rankings.BatSpeed,
-                    "Swing <br> Length" => continue,
-                    "xwOBA / <br>xERA" => &mut rankings.xERA,
-                    "FB <br>Velo" => &mut rankings.FBVelo,
-                    "FB <br>Spin" => &mut rankings.FBSpin,
-                    "CB <br>Spin" => &mut rankings.CBSpin,
-                    "Extension" => &mut rankings.Extension,
-                    name => {
-                        println!("Unknown percentile statistic: {name}");
-                        continue
-                    },
-                } = value;
+    // tells the bot owner once per column, even across restarts, when Savant's percentile table grows a column without display metadata
+    async fn report_unknown_percentile_headers(&self, ctx: &Context, player: &PlayerPercentiles) {
+        let new_headers = match self.storage.write().update(|state| player.unknown_headers.iter().filter(|header| state.mark_posted(format!("percentile-header:{header}"))).cloned().collect::<Vec<_>>()) {
+            Ok(new_headers) => new_headers,
+            Err(e) => {
+                warn!(error = format!("{e:#}"), "Could not record the reported percentile headers");
+                return
             }
-            drop(html);
-            Ok(Some(rankings))
+        };
+        if new_headers.is_empty() {
+            return
+        }
+        warn!(player = %player.name, headers = %new_headers.join(", "), "Unknown percentile statistics on a Savant page");
+        let warning = format!("Savant's percentile table has new columns that are shown without display names yet (seen on {}): {}", player.name, new_headers.iter().map(|header| format!("`{header}`")).collect::<Vec<_>>().join(", "));
+        match ctx.http.get_current_application_info().await.map(|info| info.owner) {
+            Ok(Some(owner)) => if let Err(e) = owner.direct_message(&ctx.http, CreateMessage::new().content(warning)).await {
+                warn!(error = %e, "Could not warn the bot owner");
+            },
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, "Could not get the bot owner"),
         }
+    }
 
-        fn format_percentile_ranking(name: &str, ranking: Option<u16>) -> String {
-            const PERCENTILE_WIDTH: usize = 15;
+    pub async fn savant(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        use std::fmt::Write;
 
-            if let Some(percentile) = ranking {
-                let percentile_surroundings = if percentile >= 95 { "***" } else if percentile >= 90 { "**" } else { "" };
-                format!("\n`{percentile: >3}% / [{percentile_line: <PERCENTILE_WIDTH$}]` {percentile_surroundings}{name}{percentile_surroundings}", percentile_line = "-".repeat((percentile as usize * PERCENTILE_WIDTH + 50) / 100))
-            } else {
-                String::new()
-            }
+        enum SeasonSelection {
+            Latest,
+            Year(u16),
+            Career,
         }
 
-        let Some(percentile_rankings) = get_percentile_rankings(savant_player_id)? else { return Ok(()) };
-        let message = CreateMessage::new()
-            .embed(CreateEmbed::new().title(format!("{} ({})", percentile_rankings.name, percentile_rankings.year)).thumbnail(format!("https://content.mlb.com/images/headshots/current/60x60/{savant_player_id}@3x.png")).description({
-                let mut description = String::new();
-                if percentile_rankings.hitter() {
-                    write!(description, "{}", ":cricket_game: Batting".to_owned()
-                        + &format_percentile_ranking("xwOBA", percentile_rankings.xwOBA)
-                        + &format_percentile_ranking("xBA", percentile_rankings.xBA)
-                        + &format_percentile_ranking("xSLG", percentile_rankings.xSLG)
-                        + &format_percentile_ranking("Avg EV", percentile_rankings.AvgEV)
-                        + &format_percentile_ranking("Bat Speed", percentile_rankings.BatSpeed)
-                        + &format_percentile_ranking("Barrel %", percentile_rankings.BarrelPct)
-                        + &format_percentile_ranking("Hard-Hit %", percentile_rankings.HardHitPct)
-                        + &format_percentile_ranking("Chase %", percentile_rankings.ChasePct)
-                        + &format_percentile_ranking("Whiff %", percentile_rankings.WhiffPct)
-                        + &format_percentile_ranking("K %", percentile_rankings.KPct)
-                        + &format_percentile_ranking("BB %", percentile_rankings.BBPct))?;
+        let search = arguments.text("player").unwrap_or_default();
+        // `card` swaps the text bars of a season for a rendered image
+        let card = arguments.flag("card");
+        let selection = match (arguments.flag("career"), arguments.integer("year")) {
+            (true, Some(_)) => {
+                msg.channel_id.say(&ctx.http, "Pick either a year or `career`, not both").await?;
+                return Ok(())
+            }
+            (true, None) => SeasonSelection::Career,
+            (false, Some(year)) => SeasonSelection::Year(year as u16),
+            (false, None) => SeasonSelection::Latest,
+        };
+        let Some(savant_player_id) = self.resolve_player(&ctx, &msg, &search).await? else { return Ok(()) };
+
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        // a player without a single percentile ranking has nothing to show
+        let shown_year = match selection {
+            SeasonSelection::Latest => Some(None),
+            SeasonSelection::Year(year) => Some(Some(year)),
+            SeasonSelection::Career => None,
+        };
+        let player = blocking(move || {
+            let Some(mut player) = get_percentile_rankings(savant_player_id)? else { return Ok(None) };
+            if let Some(season) = shown_year.and_then(|year| player.season_mut(year)) {
+                // the bars still make sense without the values, so a leaderboard failure isn't fatal
+                if let Err(e) = fill_metric_values(savant_player_id, season) {
+                    warn!(error = %e, "Could not get Savant stat values");
                 }
-                if percentile_rankings.fielder() {
-                    write!(description, "{}", "\n:gloves: Fielding".to_owned()
-                        + &format_percentile_ranking("Range (OAA)", percentile_rankings.OAA)
-                        + &format_percentile_ranking("Arm Strength", percentile_rankings.ArmStrength))?
+            }
+            Ok(Some(player))
+        }).await?;
+        let player = player.ok_or_else(|| CommandError::PlayerNotFound(search.clone()))?;
+        self.report_unknown_percentile_headers(&ctx, &player).await;
+        let embed = match selection {
+            SeasonSelection::Career => {
+                let (Some(first), Some(last)) = (player.seasons.first(), player.seasons.last()) else {
+                    typing_trigger.stop();
+                    msg.channel_id.say(&ctx.http, format!("{} has no percentile rankings yet", player.name)).await?;
+                    return Ok(())
+                };
+                let mut description = String::new();
+                let mut sections = Vec::new();
+                for season in &player.seasons {
+                    for (section, _) in season.sections() {
+                        if !sections.contains(&section) {
+                            sections.push(section);
+                        }
+                    }
                 }
-                if percentile_rankings.runner() {
-                    write!(description, "{}", "\n:athletic_shoe: Baserunning".to_owned()
-                        + &format_percentile_ranking("Sprint Speed", percentile_rankings.Speed))?
+                sections.sort_by_key(|section| SECTIONS.iter().position(|other| other == section));
+                for section in sections {
+                    let mut metrics = Vec::new();
+                    for metric in player.seasons.iter().flat_map(|season| season.metrics.iter()).filter(|metric| metric.section == section) {
+                        if !metrics.iter().any(|(id, _)| *id == metric.id) {
+                            metrics.push((metric.id.as_str(), metric.label.as_str()));
+                        }
+                    }
+                    writeln!(description, "{}", section.title())?;
+                    writeln!(description, "```")?;
+                    writeln!(description, "{: <13}{}", "", player.seasons.iter().map(|season| season.year.to_string()).collect::<Vec<_>>().join(" "))?;
+                    for (id, label) in metrics {
+                        let rankings = player.seasons.iter().map(|season| season.percentile(section, id)).collect::<Vec<_>>();
+                        if let Some(line) = format_percentile_trend(label, &rankings) {
+                            writeln!(description, "{line}")?;
+                        }
+                    }
+                    writeln!(description, "```")?;
                 }
-                if percentile_rankings.pitcher() {
-                    write!(description, "{}", ":baseball: Pitching".to_owned()
-                        + &format_percentile_ranking("xERA", percentile_rankings.xERA)
-                        + &format_percentile_ranking("xBA", percentile_rankings.xBA)
-                        + &format_percentile_ranking("Fastball Velo", percentile_rankings.FBVelo)
-                        + &format_percentile_ranking("Avg EV", percentile_rankings.AvgEV)
-                        + &format_percentile_ranking("Chase %", percentile_rankings.ChasePct)
-                        + &format_percentile_ranking("Whiff %", percentile_rankings.WhiffPct)
-                        + &format_percentile_ranking("K %", percentile_rankings.KPct)
-                        + &format_percentile_ranking("BB %", percentile_rankings.BBPct)
-                        + &format_percentile_ranking("Barrel %", percentile_rankings.BarrelPct)
-                        + &format_percentile_ranking("Hard-Hit %", percentile_rankings.HardHitPct)
-                        + &format_percentile_ranking("Extension", percentile_rankings.Extension))?;
+                CreateEmbed::new().title(format!("{} ({}-{})", player.name, first.year, last.year)).thumbnail(headshot_url(savant_player_id)).description(description)
+            }
+            SeasonSelection::Latest | SeasonSelection::Year(_) => {
+                let percentile_rankings = match selection {
+                    SeasonSelection::Year(year) => player.season(year),
+                    _ => player.latest(),
+                };
+                let Some(percentile_rankings) = percentile_rankings else {
+                    typing_trigger.stop();
+                    msg.channel_id.say(&ctx.http, format!("{} has no percentile rankings for that season", player.name)).await?;
+                    return Ok(())
+                };
+                if card {
+                    // a missing headshot just leaves the space empty
+                    let headshot = blocking(move || get_headshot(savant_player_id)).await.map_err(|e| warn!(error = %e, "Could not get headshot")).ok();
+                    let png = render_percentile_card(&player.name, percentile_rankings, headshot.as_ref())?;
+                    typing_trigger.stop();
+                    let embed = CreateEmbed::new().title(format!("{} ({})", player.name, percentile_rankings.year)).image("attachment://percentiles.png");
+                    msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed).add_file(CreateAttachment::bytes(png, "percentiles.png"))).await?;
+                    return Ok(())
                 }
-                description
-            }));
+                let description = percentile_rankings.sections().into_iter().map(|(section, _)| percentile_rankings.section_metrics(section).fold(section.title().to_owned(), |lines, metric| lines + &format_percentile_ranking(&metric.label, metric.percentile, metric.value.as_deref()))).collect::<Vec<_>>().join("\n");
+                CreateEmbed::new().title(format!("{} ({})", player.name, percentile_rankings.year)).thumbnail(headshot_url(savant_player_id)).description(description)
+            }
+        };
         typing_trigger.stop();
-        msg.channel_id.send_message(&ctx.http, message).await?;
+        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed)).await?;
         Ok(())
     }
 
-    pub async fn help(&self, ctx: Context, msg: Message) -> Result<()> {
-        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(CreateEmbed::new()
-            .title("Bunt Commands")
-            .field("~ev", "Gets the statcast data from the most recent ball put in play in the active braves game.", false)
-            .field("~st / ~standings", "Gets the standings in the NL East (specify AL, West/Central, and even WC) to get other stats", false)
-            .field("~savant / ~sav", "Gets the baseball savant percentile rankings data of the most likely specified player", false)
-        )).await?;
+    pub async fn arsenal(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        let search = arguments.text("pitcher").unwrap_or_default();
+        let year = arguments.integer("year").map(|year| year as u16);
+        let Some(savant_player_id) = self.resolve_player(&ctx, &msg, &search).await? else { return Ok(()) };
+
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let current_year = Local::now().date_naive().year() as u16;
+        let arsenal = blocking(move || {
+            let arsenal = get_arsenal(savant_player_id, year.unwrap_or(current_year))?;
+            // before opening day the current season's leaderboard is still empty
+            if arsenal.is_none() && year.is_none() {
+                return get_arsenal(savant_player_id, current_year - 1)
+            }
+            Ok(arsenal)
+        }).await?;
+        typing_trigger.stop();
+        let Some(arsenal) = arsenal else {
+            msg.channel_id.say(&ctx.http, "That player has no pitch arsenal data for that season").await?;
+            return Ok(())
+        };
+        let embed = CreateEmbed::new().title(format!("{} Pitch Arsenal ({})", arsenal.name, arsenal.year)).thumbnail(headshot_url(savant_player_id)).description(format_arsenal(&arsenal)?).footer(CreateEmbedFooter::new("Usage and whiff rates in %, velocity in mph, spin in rpm, break in inches, run value in runs"));
+        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed)).await?;
         Ok(())
     }
 
-    async fn on_message(&self, ctx: Context, msg: Message) -> Result<()> {
-        if msg.content.starts_with("~ev") {
-            return self.exit_velocity(ctx, msg).await;
-        } else if msg.content.starts_with("~st") || msg.content.starts_with("~standings") || msg.content.starts_with("~wc") || msg.content.starts_with("~wildcard") {
-            return self.standings(ctx, msg).await;
-        } else if msg.content.starts_with("~sav") || msg.content.starts_with("~savant") {
-            return self.savant(ctx, msg).await;
-        } else if msg.content == "~h" || msg.content == "~help" {
-            return self.help(ctx, msg).await;
+    pub async fn compare(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        let rest = arguments.text("players").unwrap_or_default();
+        // two quoted names don't need a separator
+        let searches = if let Some((first, second)) = [" vs. ", " vs ", ",", "|"].into_iter().find_map(|separator| rest.split_once(separator)) {
+            Some((first.trim(), second.trim()))
+        } else if let Some([first, second]) = arguments.words("players") {
+            Some((first.as_str(), second.as_str()))
+        } else {
+            None
+        };
+        let Some((first, second)) = searches else {
+            msg.channel_id.say(&ctx.http, format!("Usage: `{}compare <player> vs <player>`", self.prefix(msg.guild_id))).await?;
+            return Ok(())
+        };
+        let Some(first_id) = self.resolve_player(&ctx, &msg, first).await? else { return Ok(()) };
+        let Some(second_id) = self.resolve_player(&ctx, &msg, second).await? else { return Ok(()) };
+
+        let typing_trigger = TypingIndicator::start(&ctx, msg.channel_id);
+        let (first_player, second_player) = blocking(move || Ok((get_percentile_rankings(first_id)?, get_percentile_rankings(second_id)?))).await?;
+        let first = first_player.ok_or_else(|| CommandError::PlayerNotFound(first.to_owned()))?;
+        let second = second_player.ok_or_else(|| CommandError::PlayerNotFound(second.to_owned()))?;
+        self.report_unknown_percentile_headers(&ctx, &first).await;
+        self.report_unknown_percentile_headers(&ctx, &second).await;
+        let (Some(first_rankings), Some(second_rankings)) = (first.latest(), second.latest()) else {
+            let unranked = if first.latest().is_none() { &first.name } else { &second.name };
+            typing_trigger.stop();
+            msg.channel_id.say(&ctx.http, format!("{unranked} has no percentile rankings yet, so there's nothing to compare")).await?;
+            return Ok(())
+        };
+        let sections = if first_rankings.hitter() && second_rankings.hitter() {
+            vec![Section::Batting, Section::Fielding, Section::Baserunning]
+        } else if first_rankings.pitcher() && second_rankings.pitcher() {
+            vec![Section::Pitching]
+        } else {
+            let role = |rankings: &PercentileRankings| if rankings.pitcher() { "pitcher" } else { "hitter" };
+            typing_trigger.stop();
+            msg.channel_id.say(&ctx.http, format!("Can't compare {} (a {}) with {} (a {}), both players need to be hitters or both pitchers", first.name, role(first_rankings), second.name, role(second_rankings))).await?;
+            return Ok(())
+        };
+        let description = sections
+            .into_iter()
+            .filter_map(|section| {
+                let second_metrics = second_rankings.section(section);
+                let lines = first_rankings.section(section).into_iter().map(|(name, first)| format_percentile_comparison(name, first, second_metrics.iter().find(|(other, _)| *other == name).and_then(|(_, second)| *second))).collect::<String>();
+                (!lines.is_empty()).then(|| section.title().to_owned() + &lines)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let embed = CreateEmbed::new().title(format!("{} ({}) vs. {} ({})", first.name, first_rankings.year, second.name, second_rankings.year)).description(description);
+
+        typing_trigger.stop();
+        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed)).await?;
+        Ok(())
+    }
+
+    pub async fn alias(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        use std::fmt::Write;
+
+        let Some(guild_id) = msg.guild_id.map(|guild_id| guild_id.get()) else {
+            msg.channel_id.say(&ctx.http, "Player aliases can only be set in a server").await?;
+            return Ok(())
+        };
+        let prefix = self.prefix(msg.guild_id);
+        let rest = arguments.text("change").unwrap_or_default();
+        if rest.is_empty() {
+            let mut description = String::new();
+            let mut aliases = self.aliases(msg.guild_id).unwrap_or_default().into_iter().collect::<Vec<_>>();
+            aliases.sort();
+            {
+                let player_directory = self.player_directory.read();
+                for (alias, id) in aliases {
+                    writeln!(description, "`{alias}` → {}", player_directory.player_name(id).map_or_else(|| id.to_string(), str::to_owned))?;
+                }
+            }
+            if description.is_empty() {
+                description = format!("No aliases yet, add one with `{prefix}alias <nickname> = <player>`");
+            }
+            msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(CreateEmbed::new().title("Player Aliases").description(description))).await?;
+        } else if !msg.author_permissions(&ctx.cache).is_some_and(|permissions| permissions.manage_guild()) {
+            msg.channel_id.say(&ctx.http, "Only members who can manage this server can change its aliases").await?;
+        } else if let Some(alias) = rest.strip_prefix("remove ") {
+            self.storage.write().update(|state| state.guilds.entry(guild_id).or_default().aliases.remove(&normalize_name(alias)))?;
+            msg.channel_id.say(&ctx.http, format!("Removed the alias `{}`", alias.trim())).await?;
+        } else if let Some((alias, search)) = rest.split_once('=') {
+            let Some(id) = self.resolve_player(&ctx, &msg, search.trim()).await? else { return Ok(()) };
+            self.storage.write().update(|state| state.guilds.entry(guild_id).or_default().aliases.insert(normalize_name(alias), id))?;
+            msg.channel_id.say(&ctx.http, format!("`{}` now refers to player {id}", alias.trim())).await?;
+        } else {
+            msg.channel_id.say(&ctx.http, format!("Usage: `{prefix}alias <nickname> = <player>`, `{prefix}alias remove <nickname>` or `{prefix}alias` to list them")).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn help(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        use std::fmt::Write;
+
+        let prefix = self.prefix(msg.guild_id);
+        let embed = match arguments.text("command") {
+            None => COMMANDS.iter().fold(CreateEmbed::new().title("Bunt Commands"), |embed, command| embed.field(command.names().map(|name| format!("{prefix}{name}")).collect::<Vec<_>>().join(" / "), command.description, false)).footer(CreateEmbedFooter::new(format!("{prefix}help <command> explains a command's arguments, which can also be named like year=2023"))),
+            Some(name) => {
+                let Some(command) = commands::find(name.strip_prefix(prefix.as_str()).unwrap_or(&name)) else {
+                    msg.channel_id.say(&ctx.http, format!("There's no `{name}` command, `{prefix}help` lists them all")).await?;
+                    return Ok(())
+                };
+                let mut embed = CreateEmbed::new().title(format!("{prefix}{}", command.usage())).description(command.description);
+                if !command.aliases.is_empty() {
+                    embed = embed.field("Aliases", command.aliases.iter().map(|alias| format!("`{prefix}{alias}`")).collect::<Vec<_>>().join(", "), false);
+                }
+                if !command.params.is_empty() {
+                    let mut params = String::new();
+                    for param in command.params {
+                        writeln!(params, "`{}` {}", param.usage(), param.description)?;
+                    }
+                    embed = embed.field("Arguments", params, false);
+                }
+                embed.field("Examples", command.examples.iter().map(|example| format!("`{prefix}{example}`")).collect::<Vec<_>>().join("\n"), false)
+            }
+        };
+        msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(embed)).await?;
+        Ok(())
+    }
+
+    fn prefix(&self, guild_id: Option<GuildId>) -> String {
+        guild_id.and_then(|guild_id| self.storage.read().state().guilds.get(&guild_id.get())?.prefix.clone()).unwrap_or_else(|| config().prefix.clone())
+    }
+
+    pub async fn set_prefix(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        let Some(guild_id) = msg.guild_id.map(|guild_id| guild_id.get()) else {
+            msg.channel_id.say(&ctx.http, "The prefix can only be changed in a server").await?;
+            return Ok(())
+        };
+        let rest = arguments.text("prefix").unwrap_or_default();
+        if rest.is_empty() {
+            msg.channel_id.say(&ctx.http, format!("This server's prefix is `{}`, mentioning me works too", self.prefix(msg.guild_id))).await?;
+            return Ok(())
+        }
+        if !msg.author_permissions(&ctx.cache).is_some_and(|permissions| permissions.manage_guild()) {
+            msg.channel_id.say(&ctx.http, "Only members who can manage this server can change its prefix").await?;
+            return Ok(())
         }
+        let prefix = if rest == "reset" { None } else { Some(rest) };
+        if let Some(Err(e)) = prefix.as_deref().map(validate_prefix) {
+            msg.channel_id.say(&ctx.http, format!("That prefix can't be used, the {e}")).await?;
+            return Ok(())
+        }
+        self.storage.write().update(|state| state.guilds.entry(guild_id).or_default().prefix = prefix)?;
+        msg.channel_id.say(&ctx.http, format!("This server's prefix is now `{}`", self.prefix(msg.guild_id))).await?;
+        Ok(())
+    }
 
+    pub async fn purge(&self, ctx: Context, msg: Message, arguments: Arguments) -> Result<()> {
+        if ctx.http.get_current_application_info().await?.owner.map(|owner| owner.id) != Some(msg.author.id) {
+            msg.channel_id.say(&ctx.http, "Only the bot owner can purge the cache").await?;
+            return Ok(())
+        }
+        let endpoint = arguments.text("endpoint").map(|endpoint| endpoint.to_ascii_lowercase());
+        if let Some(endpoint) = &endpoint {
+            if !ENDPOINTS.iter().any(|other| other.name == endpoint) {
+                msg.channel_id.say(&ctx.http, format!("There's no cached `{endpoint}` endpoint, pick one of {}", ENDPOINTS.iter().map(|other| format!("`{}`", other.name)).collect::<Vec<_>>().join(", "))).await?;
+                return Ok(())
+            }
+        }
+        let purged = cache().purge(endpoint.as_deref());
+        info!(endpoint = endpoint.as_deref().unwrap_or("all"), purged, "Purged the cache");
+        msg.channel_id.say(&ctx.http, format!("Purged {purged} cached responses")).await?;
         Ok(())
     }
+
+    async fn on_message(&self, ctx: Context, msg: Message) -> Result<()> {
+        // mentioning the bot always works, so a forgotten prefix can't lock anyone out
+        let bot_id = ctx.cache.current_user().id;
+        let prefix = self.prefix(msg.guild_id);
+        let command = [format!("<@{bot_id}>"), format!("<@!{bot_id}>")].iter().find_map(|mention| msg.content.strip_prefix(mention.as_str())).map(str::trim_start).or_else(|| msg.content.strip_prefix(prefix.as_str()));
+        let Some(command) = command else { return Ok(()) };
+        let tokens = tokenize(command);
+        let Some((word, tokens)) = tokens.split_first() else { return Ok(()) };
+        let Some(command) = commands::find(word) else { return Ok(()) };
+
+        let span = info_span!("command", command = command.name, guild_id = msg.guild_id.map(GuildId::get), channel_id = msg.channel_id.get(), user_id = msg.author.id.get());
+        let started = Instant::now();
+        let result = self.run(ctx, msg, &prefix, command, word, tokens).instrument(span.clone()).await;
+        let duration = started.elapsed();
+        let duration_ms = duration.as_millis() as u64;
+        let outcome = span.in_scope(|| match &result {
+            Ok(()) => {
+                info!(duration_ms, "Command finished");
+                "ok"
+            }
+            Err(e) => {
+                let category = categorize(e);
+                match category {
+                    Category::Upstream(service) => warn!(duration_ms, service, error = format!("{e:#}"), "Command failed upstream"),
+                    Category::Internal => error!(duration_ms, error = format!("{e:#}"), "Command failed"),
+                    ref category => info!(duration_ms, ?category, "Command finished without a result"),
+                }
+                category.label()
+            }
+        });
+        metrics().record_command(command.name, outcome, duration);
+        result
+    }
+
+    async fn run(&self, ctx: Context, msg: Message, prefix: &str, command: &'static Command, word: &str, tokens: &[String]) -> Result<()> {
+        let arguments = match command.parse(tokens) {
+            Ok(arguments) => arguments,
+            Err(e) => {
+                info!(error = %e, "Usage error");
+                msg.channel_id.say(&ctx.http, format!("{e}, usage: `{prefix}{}`", command.usage())).await?;
+                return Ok(())
+            }
+        };
+        match command.name {
+            "ev" => self.exit_velocity(ctx, msg, arguments).await,
+            "pitch" => self.pitch(ctx, msg, arguments).await,
+            "standings" => {
+                let wild_card = word.eq_ignore_ascii_case("wc") || word.eq_ignore_ascii_case("wildcard");
+                self.standings(ctx, msg, arguments, wild_card).await
+            }
+            "odds" => self.odds(ctx, msg, arguments).await,
+            "magic" => self.magic(ctx, msg, arguments).await,
+            "wp" => self.win_probability(ctx, msg, arguments).await,
+            "mvp" => self.top_plays(ctx, msg, arguments).await,
+            "savant" => self.savant(ctx, msg, arguments).await,
+            "compare" => self.compare(ctx, msg, arguments).await,
+            "arsenal" => self.arsenal(ctx, msg, arguments).await,
+            "alias" => self.alias(ctx, msg, arguments).await,
+            "prefix" => self.set_prefix(ctx, msg, arguments).await,
+            "purge" => self.purge(ctx, msg, arguments).await,
+            "help" => self.help(ctx, msg, arguments).await,
+            _ => Ok(()),
+        }
+    }
 }
 
 #[async_trait]
 impl EventHandler for Handler {
+    async fn ready(&self, _: Context, ready: Ready) {
+        info!(user = %ready.user.name, guilds = ready.guilds.len(), "Connected to discord");
+        metrics().set_gateway_connected(true);
+    }
+
+    async fn shard_stage_update(&self, _: Context, event: ShardStageUpdateEvent) {
+        metrics().set_gateway_connected(event.new == ConnectionStage::Connected);
+    }
+
     async fn message(&self, ctx: Context, msg: Message) {
-        if let Err(e) = self.on_message(ctx, msg).await {
-            println!("Error sending message: {e}")
+        let (http, channel_id) = (ctx.http.clone(), msg.channel_id);
+        let Err(e) = self.on_message(ctx, msg).await else { return };
+        let category = categorize(&e);
+        let color = if category == Category::Internal { 0xD62728 } else { 0xE0A526 };
+        let embed = CreateEmbed::new().title(category.title()).description(category.description()).color(color);
+        if let Err(e) = channel_id.send_message(&http, CreateMessage::new().embed(embed)).await {
+            error!(error = %e, "Could not send the error reply")
         }
     }
 }
 
 #[tokio::main]
 async fn main() {
-    let intents = GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT | GatewayIntents::GUILD_MESSAGE_TYPING;
+    let args = Args::parse();
+    match Config::load(&args) {
+        Ok(loaded) => {
+            init_logging(&loaded);
+            config::init(loaded)
+        }
+        Err(e) => {
+            eprintln!("Invalid configuration: {e:#}");
+            std::process::exit(2)
+        }
+    }
 
-    let token = env::var("BUNT_DISCORD_TOKEN").expect("Expected a token to be in the environment variables");
-    let mut client = Client::builder(&token, intents).event_handler(Handler { current_game_id: RwLock::new(None) }).await.expect("Error creating client");
+    if let Some(savant_player_id) = args.capture_fixture {
+        match savant::capture_fixture(savant_player_id, Path::new(FIXTURES_DIRECTORY)) {
+            Ok(paths) => paths.iter().for_each(|path| println!("Wrote {}", path.display())),
+            Err(e) => {
+                eprintln!("Could not capture fixture: {e}");
+                std::process::exit(1)
+            }
+        }
+        return
+    }
 
-    std::fs::write("download.html", ureq::get(&format!("https://baseballsavant.mlb.com/savant-player/660271?stats=statcast-r-hitting-mlb")).call().unwrap().into_string().unwrap()).unwrap();
+    let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT | GatewayIntents::GUILD_MESSAGE_TYPING;
+
+    let storage = match Storage::load(&config().storage_path) {
+        Ok(storage) => storage,
+        Err(e) => {
+            error!(error = format!("{e:#}"), "Could not load storage");
+            std::process::exit(1)
+        }
+    };
+    if let Some(address) = config().metrics_address() {
+        let listener = match metrics::bind(address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!(error = format!("{e:#}"), "Could not start the metrics server");
+                std::process::exit(1)
+            }
+        };
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(listener).await {
+                error!(error = format!("{e:#}"), "Metrics server stopped");
+            }
+        });
+    }
+    let mut client = Client::builder(&config().token, intents).event_handler(Handler { storage: Arc::new(RwLock::new(storage)), player_directory: Arc::new(RwLock::new(PlayerDirectory::load())) }).await.expect("Error creating client");
 
     if let Err(e) = client.start().await {
-        println!("Error running client: {e}");
+        error!(error = %e, "Error running client");
     }
 }
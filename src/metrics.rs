@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+
+use crate::http::Upstream;
+
+// seconds
+const DURATION_BUCKETS: [f64; 9] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+#[derive(Default)]
+struct Histogram {
+    // cumulative, like prometheus wants them
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, count) in DURATION_BUCKETS.iter().zip(self.buckets.iter_mut()) {
+            if seconds <= *bucket {
+                *count += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+// everything is keyed by label values, sorted so the output is stable
+#[derive(Default)]
+pub struct Metrics {
+    // (command, outcome)
+    commands: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    command_durations: Mutex<BTreeMap<&'static str, Histogram>>,
+    last_command: AtomicU64,
+    // (upstream, result)
+    upstream_requests: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    upstream_retries: Mutex<BTreeMap<&'static str, u64>>,
+    // (endpoint, result)
+    cache_requests: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    gateway_connected: AtomicBool,
+}
+
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::default)
+}
+
+impl Metrics {
+    pub fn record_command(&self, command: &'static str, outcome: &'static str, duration: Duration) {
+        *self.commands.lock().entry((command, outcome)).or_default() += 1;
+        self.command_durations.lock().entry(command).or_default().observe(duration.as_secs_f64());
+        self.last_command.store(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(), Ordering::Relaxed);
+    }
+
+    pub fn record_upstream(&self, upstream: Upstream, success: bool) {
+        *self.upstream_requests.lock().entry((upstream.label(), if success { "success" } else { "failure" })).or_default() += 1;
+    }
+
+    pub fn record_retry(&self, upstream: Upstream) {
+        *self.upstream_retries.lock().entry(upstream.label()).or_default() += 1;
+    }
+
+    pub fn record_cache(&self, endpoint: &'static str, hit: bool) {
+        *self.cache_requests.lock().entry((endpoint, if hit { "hit" } else { "miss" })).or_default() += 1;
+    }
+
+    pub fn set_gateway_connected(&self, connected: bool) {
+        self.gateway_connected.store(connected, Ordering::Relaxed);
+    }
+
+    // the prometheus text format
+    pub fn render(&self) -> Result<String> {
+        let mut text = String::new();
+        writeln!(text, "# HELP bunt_commands_total Commands run, by how they ended")?;
+        writeln!(text, "# TYPE bunt_commands_total counter")?;
+        for ((command, outcome), count) in self.commands.lock().iter() {
+            writeln!(text, "bunt_commands_total{{command=\"{command}\",outcome=\"{outcome}\"}} {count}")?;
+        }
+        writeln!(text, "# HELP bunt_command_duration_seconds Time from a command arriving to its reply")?;
+        writeln!(text, "# TYPE bunt_command_duration_seconds histogram")?;
+        for (command, histogram) in self.command_durations.lock().iter() {
+            for (bucket, count) in DURATION_BUCKETS.iter().zip(histogram.buckets) {
+                writeln!(text, "bunt_command_duration_seconds_bucket{{command=\"{command}\",le=\"{bucket}\"}} {count}")?;
+            }
+            writeln!(text, "bunt_command_duration_seconds_bucket{{command=\"{command}\",le=\"+Inf\"}} {}", histogram.count)?;
+            writeln!(text, "bunt_command_duration_seconds_sum{{command=\"{command}\"}} {}", histogram.sum)?;
+            writeln!(text, "bunt_command_duration_seconds_count{{command=\"{command}\"}} {}", histogram.count)?;
+        }
+        writeln!(text, "# HELP bunt_last_command_timestamp_seconds When the last command finished")?;
+        writeln!(text, "# TYPE bunt_last_command_timestamp_seconds gauge")?;
+        writeln!(text, "bunt_last_command_timestamp_seconds {}", self.last_command.load(Ordering::Relaxed))?;
+        writeln!(text, "# HELP bunt_upstream_requests_total Requests to savant, the stats api and the image server")?;
+        writeln!(text, "# TYPE bunt_upstream_requests_total counter")?;
+        for ((upstream, result), count) in self.upstream_requests.lock().iter() {
+            writeln!(text, "bunt_upstream_requests_total{{upstream=\"{upstream}\",result=\"{result}\"}} {count}")?;
+        }
+        writeln!(text, "# HELP bunt_upstream_retries_total Failed requests that were retried")?;
+        writeln!(text, "# TYPE bunt_upstream_retries_total counter")?;
+        for (upstream, count) in self.upstream_retries.lock().iter() {
+            writeln!(text, "bunt_upstream_retries_total{{upstream=\"{upstream}\"}} {count}")?;
+        }
+        writeln!(text, "# HELP bunt_cache_requests_total Cacheable requests, by whether the cache could answer them")?;
+        writeln!(text, "# TYPE bunt_cache_requests_total counter")?;
+        for ((endpoint, result), count) in self.cache_requests.lock().iter() {
+            writeln!(text, "bunt_cache_requests_total{{endpoint=\"{endpoint}\",result=\"{result}\"}} {count}")?;
+        }
+        writeln!(text, "# HELP bunt_gateway_connected Whether the discord gateway connection is up")?;
+        writeln!(text, "# TYPE bunt_gateway_connected gauge")?;
+        writeln!(text, "bunt_gateway_connected {}", self.gateway_connected.load(Ordering::Relaxed) as u8)?;
+        Ok(text)
+    }
+
+    fn route(&self, path: &str) -> Result<(&'static str, &'static str, String)> {
+        Ok(match path {
+            "/metrics" => ("200 OK", "text/plain; version=0.0.4", self.render()?),
+            // the bot can't answer anything without the gateway
+            "/healthz" if self.gateway_connected.load(Ordering::Relaxed) => ("200 OK", "text/plain", "ok\n".to_owned()),
+            "/healthz" => ("503 Service Unavailable", "text/plain", "gateway disconnected\n".to_owned()),
+            _ => ("404 Not Found", "text/plain", "not found\n".to_owned()),
+        })
+    }
+}
+
+async fn respond(mut stream: TcpStream) -> Result<()> {
+    let mut request = [0; 1024];
+    let read = stream.read(&mut request).await?;
+    let request = String::from_utf8_lossy(&request[..read]);
+    let path = request.lines().next().and_then(|line| line.split_whitespace().nth(1)).unwrap_or("/");
+    let (status, content_type, body) = metrics().route(path)?;
+    stream.write_all(format!("HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()).as_bytes()).await?;
+    Ok(())
+}
+
+// bound before the client starts, so a taken port stops the bot instead of leaving it without health checks
+pub async fn bind(address: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(address).await.with_context(|| format!("Could not listen on {address}"))
+}
+
+pub async fn serve(listener: TcpListener) -> Result<()> {
+    if let Ok(address) = listener.local_addr() {
+        info!(%address, "Serving metrics and health checks");
+    }
+    loop {
+        let (stream, _) = listener.accept().await.context("Could not accept a metrics connection")?;
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream).await {
+                debug!(error = %e, "Could not answer a metrics request");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_counted_and_timed() {
+        let metrics = Metrics::default();
+        metrics.record_command("savant", "ok", Duration::from_millis(800));
+        metrics.record_command("savant", "ok", Duration::from_secs(3));
+        metrics.record_command("savant", "upstream", Duration::from_secs(90));
+        metrics.record_upstream(Upstream::Savant, false);
+        metrics.record_retry(Upstream::StatsApi);
+        metrics.record_cache("standings", true);
+        let text = metrics.render().unwrap();
+        assert!(text.contains("bunt_commands_total{command=\"savant\",outcome=\"ok\"} 2\n"));
+        assert!(text.contains("bunt_commands_total{command=\"savant\",outcome=\"upstream\"} 1\n"));
+        assert!(text.contains("bunt_command_duration_seconds_bucket{command=\"savant\",le=\"0.5\"} 0\n"));
+        assert!(text.contains("bunt_command_duration_seconds_bucket{command=\"savant\",le=\"1\"} 1\n"));
+        assert!(text.contains("bunt_command_duration_seconds_bucket{command=\"savant\",le=\"60\"} 2\n"));
+        assert!(text.contains("bunt_command_duration_seconds_bucket{command=\"savant\",le=\"+Inf\"} 3\n"));
+        assert!(text.contains("bunt_command_duration_seconds_sum{command=\"savant\"} 93.8\n"));
+        assert!(text.contains("bunt_upstream_requests_total{upstream=\"savant\",result=\"failure\"} 1\n"));
+        assert!(text.contains("bunt_upstream_retries_total{upstream=\"statsapi\"} 1\n"));
+        assert!(text.contains("bunt_cache_requests_total{endpoint=\"standings\",result=\"hit\"} 1\n"));
+    }
+
+    #[test]
+    fn health_follows_the_gateway() {
+        let metrics = Metrics::default();
+        assert_eq!(metrics.route("/healthz").unwrap().0, "503 Service Unavailable");
+        metrics.set_gateway_connected(true);
+        assert_eq!(metrics.route("/healthz").unwrap().0, "200 OK");
+        assert!(metrics.route("/metrics").unwrap().2.contains("bunt_gateway_connected 1\n"));
+        assert_eq!(metrics.route("/").unwrap().0, "404 Not Found");
+    }
+
+    #[test]
+    fn a_taken_address_fails_to_bind() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+        runtime.block_on(async {
+            let listener = bind("127.0.0.1:0".parse().unwrap()).await.unwrap();
+            assert!(bind(listener.local_addr().unwrap()).await.is_err());
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::season::{ScheduledGame, TeamRecord};
+use crate::WILD_CARD_SPOTS;
+
+pub const SIMULATIONS: usize = 10_000;
+// every team is regressed towards .500 by this many phantom games before simulating
+const REGRESSION_GAMES: f64 = 40.0;
+const HOME_FIELD_ADVANTAGE: f64 = 0.02;
+const DIVISION_WINNERS_WITH_BYE: usize = 2;
+
+pub struct PlayoffOdds {
+    pub division: f64,
+    pub bye: f64,
+    pub playoffs: f64,
+}
+
+fn log5(a: f64, b: f64) -> f64 {
+    (a - a * b) / (a + b - 2.0 * a * b)
+}
+
+pub fn simulate(teams: &[TeamRecord], remaining: &[ScheduledGame], simulations: usize, rng: &mut impl Rng) -> Vec<PlayoffOdds> {
+    let indices = teams.iter().enumerate().map(|(idx, team)| (team.id, idx)).collect::<HashMap<_, _>>();
+    let strengths = teams.iter().map(|team| (team.wins as f64 + REGRESSION_GAMES / 2.0) / (team.games_played() as f64 + REGRESSION_GAMES)).collect::<Vec<_>>();
+    let games = remaining
+        .iter()
+        .filter_map(|game| {
+            let (home, away) = (*indices.get(&game.home_id)?, *indices.get(&game.away_id)?);
+            Some((home, away, (log5(strengths[home], strengths[away]) + HOME_FIELD_ADVANTAGE).clamp(0.0, 1.0)))
+        })
+        .collect::<Vec<_>>();
+    let mut leagues = HashMap::<i64, HashMap<i64, Vec<usize>>>::new();
+    for (idx, team) in teams.iter().enumerate() {
+        leagues.entry(team.league_id).or_default().entry(team.division_id).or_default().push(idx);
+    }
+
+    let mut division_counts = vec![0_usize; teams.len()];
+    let mut bye_counts = vec![0_usize; teams.len()];
+    let mut playoff_counts = vec![0_usize; teams.len()];
+    for _ in 0..simulations {
+        let mut wins = teams.iter().map(|team| team.wins).collect::<Vec<_>>();
+        for &(home, away, home_win_probability) in &games {
+            wins[if rng.gen_bool(home_win_probability) { home } else { away }] += 1;
+        }
+        // ties are broken by a coin flip rather than by the real tiebreaker rules
+        let tiebreakers = (0..teams.len()).map(|_| rng.gen::<u32>()).collect::<Vec<_>>();
+        let seed = |idx: &usize| (wins[*idx], tiebreakers[*idx]);
+
+        for divisions in leagues.values() {
+            let mut division_winners = divisions.values().filter_map(|division| division.iter().copied().max_by_key(seed)).collect::<Vec<_>>();
+            division_winners.sort_by_key(|idx| std::cmp::Reverse(seed(idx)));
+            let mut wild_cards = divisions.values().flatten().copied().filter(|idx| !division_winners.contains(idx)).collect::<Vec<_>>();
+            wild_cards.sort_by_key(|idx| std::cmp::Reverse(seed(idx)));
+
+            for (rank, &idx) in division_winners.iter().enumerate() {
+                division_counts[idx] += 1;
+                playoff_counts[idx] += 1;
+                if rank < DIVISION_WINNERS_WITH_BYE {
+                    bye_counts[idx] += 1;
+                }
+            }
+            for &idx in wild_cards.iter().take(WILD_CARD_SPOTS) {
+                playoff_counts[idx] += 1;
+            }
+        }
+    }
+
+    teams
+        .iter()
+        .enumerate()
+        .map(|(idx, _)| PlayoffOdds {
+            division: division_counts[idx] as f64 / simulations as f64,
+            bye: bye_counts[idx] as f64 / simulations as f64,
+            playoffs: playoff_counts[idx] as f64 / simulations as f64,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    // one league of three divisions with three teams each, team ids are 1 to 9 and divisions 1 to 3
+    fn league(records: [(u32, u32); 9]) -> Vec<TeamRecord> {
+        records.iter().enumerate().map(|(idx, &(wins, losses))| TeamRecord {
+            id: idx as i64 + 1,
+            name: format!("Team {}", idx + 1),
+            location_name: String::new(),
+            abbreviation: String::new(),
+            league_id: crate::season::NL_LEAGUE_ID,
+            division_id: idx as i64 / 3 + 1,
+            division_name: format!("Division {}", idx / 3 + 1),
+            wins,
+            losses,
+        }).collect()
+    }
+
+    fn game(home_id: i64, away_id: i64) -> ScheduledGame {
+        ScheduledGame { home_id, away_id, winner_id: None }
+    }
+
+    // every team hosts each of its two division rivals this many times
+    fn division_games(times: usize) -> Vec<ScheduledGame> {
+        let mut games = Vec::new();
+        for home in 1..=9 {
+            for away in (1..=9).filter(|away| *away != home && (away - 1) / 3 == (home - 1) / 3) {
+                games.extend((0..times).map(|_| game(home, away)));
+            }
+        }
+        games
+    }
+
+    fn simulate_seeded(teams: &[TeamRecord], remaining: &[ScheduledGame]) -> Vec<PlayoffOdds> {
+        simulate(teams, remaining, 2_000, &mut StdRng::seed_from_u64(26))
+    }
+
+    #[test]
+    fn clinched_and_eliminated_teams_are_certain() {
+        // team 1 leads its division by more games than are left, team 9 trails the last wild card by more
+        let teams = league([(100, 56), (80, 76), (79, 77), (90, 66), (88, 68), (87, 69), (91, 65), (86, 70), (60, 96)]);
+        let odds = simulate_seeded(&teams, &division_games(1));
+        assert_eq!(odds[0].division, 1.0);
+        assert_eq!(odds[0].playoffs, 1.0);
+        assert_eq!(odds[8].division, 0.0);
+        assert_eq!(odds[8].playoffs, 0.0);
+        assert_eq!(odds[8].bye, 0.0);
+    }
+
+    #[test]
+    fn division_odds_add_up_to_one() {
+        let teams = league([(80, 76), (79, 77), (78, 78), (81, 75), (81, 75), (70, 86), (75, 81), (76, 80), (77, 79)]);
+        let odds = simulate_seeded(&teams, &division_games(3));
+        for division in odds.chunks(3) {
+            assert!((division.iter().map(|odds| odds.division).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+        // three division winners and three wild cards every time
+        assert!((odds.iter().map(|odds| odds.playoffs).sum::<f64>() - 6.0).abs() < 1e-9);
+        assert!((odds.iter().map(|odds| odds.bye).sum::<f64>() - DIVISION_WINNERS_WITH_BYE as f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn opening_day_records_are_regressed_instead_of_dividing_by_zero() {
+        let teams = league([(0, 0); 9]);
+        let odds = simulate_seeded(&teams, &division_games(10));
+        assert!(odds.iter().all(|odds| odds.division.is_finite() && odds.bye.is_finite() && odds.playoffs.is_finite()));
+        assert!(odds.iter().all(|odds| odds.division > 0.0 && odds.division < 1.0));
+        assert_eq!(log5(0.5, 0.5), 0.5);
+    }
+}
@@ -0,0 +1,124 @@
+use serde_json::Value;
+
+// half the plate's 17 inches plus the radius of a ball, in feet
+pub const ZONE_HALF_WIDTH: f64 = 0.83;
+const BALL_RADIUS: f64 = 0.121;
+
+pub struct LivePitch {
+    pub at_bat: u64,
+    pub inning: Option<u64>,
+    pub batting_team_id: Option<i64>,
+    pub batter_name: String,
+    pub pitcher_name: String,
+    pub left_handed_batter: bool,
+    pub pitch_name: String,
+    pub velocity: Option<f64>,
+    pub spin: Option<f64>,
+    // inches of induced vertical and horizontal break
+    pub vertical_break: Option<f64>,
+    pub horizontal_break: Option<f64>,
+    // feet from the middle of the plate and above the ground, from the catcher's view
+    pub x: Option<f64>,
+    pub z: Option<f64>,
+    pub zone_top: Option<f64>,
+    pub zone_bottom: Option<f64>,
+    // "B", "S" or "X" for a ball put in play
+    pub call: String,
+    pub description: String,
+    pub balls: u64,
+    pub strikes: u64,
+    // the result of the plate appearance, only on its last pitch
+    pub result: Option<String>,
+}
+
+// the gf feed sends some numbers as strings
+fn float(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str()?.parse().ok())
+}
+
+impl LivePitch {
+    fn from_gf(pitch: &Value) -> Option<LivePitch> {
+        Some(LivePitch {
+            at_bat: pitch["ab_number"].as_u64()?,
+            inning: pitch["inning"].as_u64(),
+            batting_team_id: pitch["team_batting_id"].as_i64(),
+            batter_name: pitch["batter_name"].as_str()?.to_owned(),
+            pitcher_name: pitch["pitcher_name"].as_str()?.to_owned(),
+            left_handed_batter: pitch["stand"].as_str() == Some("L"),
+            pitch_name: pitch["pitch_name"].as_str().unwrap_or("Unknown pitch").to_owned(),
+            velocity: float(&pitch["start_speed"]),
+            spin: float(&pitch["spin_rate"]),
+            vertical_break: float(&pitch["inducedBreakZ"]),
+            horizontal_break: float(&pitch["breakX"]),
+            x: float(&pitch["px"]),
+            z: float(&pitch["pz"]),
+            zone_top: float(&pitch["sz_top"]),
+            zone_bottom: float(&pitch["sz_bot"]),
+            call: pitch["call"].as_str().unwrap_or_default().to_owned(),
+            description: pitch["call_name"].as_str().or(pitch["description"].as_str()).unwrap_or_default().to_owned(),
+            balls: pitch["balls"].as_u64().unwrap_or_default(),
+            strikes: pitch["strikes"].as_u64().unwrap_or_default(),
+            result: pitch["des"].as_str().filter(|des| !des.is_empty()).map(str::to_owned),
+        })
+    }
+
+    pub fn location(&self) -> Option<String> {
+        let (x, z, top, bottom) = (self.x?, self.z?, self.zone_top?, self.zone_bottom?);
+        let height = if z - BALL_RADIUS > top { Some("high") } else if z + BALL_RADIUS < bottom { Some("low") } else { None };
+        // a right-handed batter stands on the catcher's left
+        let inside = (x < 0.0) != self.left_handed_batter;
+        let side = if x.abs() > ZONE_HALF_WIDTH { Some(if inside { "inside" } else { "away" }) } else { None };
+        Some(match (height, side) {
+            (None, None) => "In the zone".to_owned(),
+            (Some("high"), None) => "Above the zone".to_owned(),
+            (Some(_), None) => "Below the zone".to_owned(),
+            (None, Some(side)) => format!("Off the plate {side}"),
+            (Some(height), Some(side)) => format!("Out of the zone, {height} and {side}"),
+        })
+    }
+}
+
+// every pitch of the game, in the order it was thrown
+pub fn get_game_pitches(game: &Value) -> Vec<LivePitch> {
+    let mut pitches = ["team_home", "team_away"]
+        .into_iter()
+        .flat_map(|team| game[team].as_array().into_iter().flatten())
+        .filter_map(|pitch| Some((pitch["game_total_pitches"].as_u64()?, LivePitch::from_gf(pitch)?)))
+        .collect::<Vec<_>>();
+    pitches.sort_by_key(|(number, _)| *number);
+    pitches.into_iter().map(|(_, pitch)| pitch).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pitch(number: u64, at_bat: u64, x: f64, z: f64, stand: &str) -> Value {
+        serde_json::json!({
+            "game_total_pitches": number, "ab_number": at_bat, "batter_name": "Matt Olson", "pitcher_name": "Zack Wheeler", "stand": stand,
+            "pitch_name": "Sinker", "start_speed": "95.4", "spin_rate": 2230, "inducedBreakZ": 9.1, "breakX": 14.2,
+            "px": x, "pz": z, "sz_top": 3.4, "sz_bot": 1.6, "call": "S", "call_name": "Called Strike", "balls": 1, "strikes": 2,
+        })
+    }
+
+    #[test]
+    fn pitches_from_both_teams_are_put_in_order() {
+        let game = serde_json::json!({ "team_home": [pitch(3, 2, 0.0, 2.5, "L")], "team_away": [pitch(2, 1, 0.0, 2.5, "R"), pitch(1, 1, 0.0, 2.5, "R")] });
+        let pitches = get_game_pitches(&game);
+        assert_eq!(pitches.iter().map(|pitch| pitch.at_bat).collect::<Vec<_>>(), [1, 1, 2]);
+        assert_eq!(pitches[2].velocity, Some(95.4));
+        assert!(pitches[2].left_handed_batter);
+    }
+
+    #[test]
+    fn locations_are_relative_to_the_batter() {
+        let location = |x: f64, z: f64, stand: &str| LivePitch::from_gf(&pitch(1, 1, x, z, stand)).unwrap().location().unwrap();
+        assert_eq!(location(0.2, 2.5, "R"), "In the zone");
+        assert_eq!(location(0.0, 3.9, "R"), "Above the zone");
+        assert_eq!(location(-1.2, 2.5, "R"), "Off the plate inside");
+        assert_eq!(location(-1.2, 2.5, "L"), "Off the plate away");
+        assert_eq!(location(1.3, 0.9, "L"), "Out of the zone, low and inside");
+        // the edge of the ball catching the zone is still a strike
+        assert_eq!(location(0.8, 3.45, "R"), "In the zone");
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::config;
+use crate::get_with_sleep;
+use crate::savant::{PlayerCandidate, PlayerSearch};
+
+pub const PLAYER_DIRECTORY_PATH: &str = "players.json";
+// players who haven't appeared in this many seasons drop out of the directory
+const DIRECTORY_SEASONS: i32 = 3;
+const FUZZY_MATCH_THRESHOLD: f64 = 0.9;
+// the best fuzzy match is only taken on its own when it beats the runner-up by this much
+const FUZZY_MATCH_MARGIN: f64 = 0.04;
+// a refresh that failed isn't tried again for this long, lookups use the old directory meanwhile
+const FAILED_REFRESH_BACKOFF_MINUTES: i64 = 30;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DirectoryPlayer {
+    pub id: usize,
+    pub name: String,
+    pub last_name: String,
+    pub nickname: Option<String>,
+    pub position: Option<String>,
+    pub team: Option<String>,
+    pub first_year: Option<u64>,
+    pub last_year: u64,
+}
+
+impl DirectoryPlayer {
+    fn candidate(&self) -> PlayerCandidate {
+        PlayerCandidate { id: self.id, name: self.name.clone(), position: self.position.clone(), team: self.team.clone(), first_year: self.first_year, last_year: Some(self.last_year) }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct PlayerDirectory {
+    updated: Option<DateTime<Utc>>,
+    players: Vec<DirectoryPlayer>,
+    #[serde(skip)]
+    failed_refresh: Option<DateTime<Utc>>,
+}
+
+// lowercases, strips diacritics and punctuation so "Acuña Jr." and "acuna jr" compare equal
+pub fn normalize_name(name: &str) -> String {
+    name.nfd()
+        .filter(|char| char.is_ascii())
+        .map(|char| if char == '-' { ' ' } else { char.to_ascii_lowercase() })
+        .filter(|char| char.is_ascii_alphanumeric() || *char == ' ')
+        .collect::<String>()
+        .split_ascii_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl PlayerDirectory {
+    pub fn load() -> PlayerDirectory {
+        std::fs::read(&config().player_directory_path).ok().and_then(|bytes| serde_json::from_slice::<PlayerDirectory>(&bytes).ok()).unwrap_or_default()
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.updated.is_none_or(|updated| Utc::now() - updated > Duration::hours(config().directory_refresh_hours as i64)) && self.failed_refresh.is_none_or(|failed| Utc::now() - failed > Duration::minutes(FAILED_REFRESH_BACKOFF_MINUTES))
+    }
+
+    // builds a new directory instead of changing this one, so lookups can keep using the old one during the download
+    pub fn download() -> Result<PlayerDirectory> {
+        let current_year = Local::now().date_naive().year();
+        let mut players = HashMap::<usize, DirectoryPlayer>::new();
+        let mut teams = HashMap::<i64, String>::new();
+        for team in get_with_sleep(&format!("{}/api/v1/teams?sportId=1", config().statsapi_url))?["teams"].as_array().context("Could not get teams")? {
+            teams.insert(team["id"].as_i64().context("Could not get team id")?, team["abbreviation"].as_str().context("Could not get team abbreviation")?.to_owned());
+        }
+        for season in current_year + 1 - DIRECTORY_SEASONS..=current_year {
+            for person in get_with_sleep(&format!("{}/api/v1/sports/1/players?season={season}", config().statsapi_url))?["people"].as_array().context("Could not get players")? {
+                let Some(id) = person["id"].as_u64().map(|id| id as usize) else { continue };
+                let Some(name) = person["fullName"].as_str() else { continue };
+                players.insert(id, DirectoryPlayer {
+                    id,
+                    name: name.to_owned(),
+                    last_name: person["lastName"].as_str().unwrap_or(name).to_owned(),
+                    nickname: person["nickName"].as_str().map(str::to_owned),
+                    position: person["primaryPosition"]["abbreviation"].as_str().map(str::to_owned),
+                    team: person["currentTeam"]["id"].as_i64().and_then(|team_id| teams.get(&team_id).cloned()),
+                    first_year: person["mlbDebutDate"].as_str().and_then(|date| date.get(..4)?.parse().ok()),
+                    last_year: season as u64,
+                });
+            }
+        }
+        let directory = PlayerDirectory { updated: Some(Utc::now()), players: players.into_values().collect(), failed_refresh: None };
+        std::fs::write(&config().player_directory_path, serde_json::to_vec(&directory)?).with_context(|| format!("Could not write {}", config().player_directory_path.display()))?;
+        Ok(directory)
+    }
+
+    pub fn refresh_failed(&mut self) {
+        self.failed_refresh = Some(Utc::now());
+    }
+
+    pub fn player_name(&self, id: usize) -> Option<&str> {
+        self.players.iter().find(|player| player.id == id).map(|player| player.name.as_str())
+    }
+
+    // `None` means nothing in the directory came close and the search should go to the network
+    pub fn search(&self, aliases: Option<&HashMap<String, usize>>, search: &str) -> Option<PlayerSearch> {
+        let search = normalize_name(search);
+        if search.is_empty() {
+            return None
+        }
+        if let Some(id) = aliases.and_then(|aliases| aliases.get(&search)) {
+            return Some(PlayerSearch::Found(*id))
+        }
+
+        let exact_matches = self.players.iter().filter(|player| normalize_name(&player.name) == search || player.nickname.as_deref().is_some_and(|nickname| normalize_name(nickname) == search)).collect::<Vec<_>>();
+        let exact_matches = if exact_matches.is_empty() { self.players.iter().filter(|player| normalize_name(&player.last_name) == search).collect() } else { exact_matches };
+        if let Some(search) = Self::to_search(exact_matches) {
+            return Some(search)
+        }
+
+        let mut fuzzy_matches = self.players
+            .iter()
+            .map(|player| (player, strsim::jaro_winkler(&normalize_name(&player.name), &search).max(strsim::jaro_winkler(&normalize_name(&player.last_name), &search))))
+            .filter(|(_, score)| *score >= FUZZY_MATCH_THRESHOLD)
+            .collect::<Vec<_>>();
+        fuzzy_matches.sort_by(|(a_player, a), (b_player, b)| b.total_cmp(a).then(b_player.last_year.cmp(&a_player.last_year)));
+        match &*fuzzy_matches {
+            [(best, best_score), (_, second_score), ..] if best_score - second_score >= FUZZY_MATCH_MARGIN => Some(PlayerSearch::Found(best.id)),
+            _ => Self::to_search(fuzzy_matches.into_iter().map(|(player, _)| player).collect()),
+        }
+    }
+
+    fn to_search(mut players: Vec<&DirectoryPlayer>) -> Option<PlayerSearch> {
+        match &*players {
+            [] => None,
+            [player] => Some(PlayerSearch::Found(player.id)),
+            _ => {
+                players.sort_by_key(|player| std::cmp::Reverse(player.last_year));
+                Some(PlayerSearch::Candidates(players.into_iter().map(DirectoryPlayer::candidate).collect()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: usize, name: &str, last_name: &str, nickname: Option<&str>, last_year: u64) -> DirectoryPlayer {
+        DirectoryPlayer { id, name: name.to_owned(), last_name: last_name.to_owned(), nickname: nickname.map(str::to_owned), position: None, team: None, first_year: None, last_year }
+    }
+
+    fn directory(players: Vec<DirectoryPlayer>) -> PlayerDirectory {
+        PlayerDirectory { updated: Some(Utc::now()), players, failed_refresh: None }
+    }
+
+    fn braves() -> PlayerDirectory {
+        directory(vec![
+            player(660670, "Ronald Acuña Jr.", "Acuña", None, 2024),
+            player(671739, "Michael Harris II", "Harris", Some("Money Mike"), 2024),
+            player(621566, "Matt Olson", "Olson", None, 2024),
+            player(518876, "Merrill Kelly", "Kelly", None, 2024),
+            player(641743, "Trevor Kelley", "Kelley", None, 2023),
+        ])
+    }
+
+    fn found(search: Option<PlayerSearch>) -> Option<usize> {
+        match search {
+            Some(PlayerSearch::Found(id)) => Some(id),
+            _ => None,
+        }
+    }
+
+    fn candidates(search: Option<PlayerSearch>) -> Vec<usize> {
+        match search {
+            Some(PlayerSearch::Candidates(candidates)) => candidates.iter().map(|candidate| candidate.id).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn names_are_compared_without_accents_or_punctuation() {
+        assert_eq!(normalize_name("Ronald Acuña Jr."), "ronald acuna jr");
+        assert_eq!(normalize_name("  Jazz Chisholm-Jr "), "jazz chisholm jr");
+        assert_eq!(found(braves().search(None, "acuna")), Some(660670));
+        assert_eq!(found(braves().search(None, "RONALD ACUÑA JR")), Some(660670));
+        assert!(braves().search(None, "...").is_none());
+    }
+
+    #[test]
+    fn nicknames_find_their_player() {
+        assert_eq!(found(braves().search(None, "money mike")), Some(671739));
+    }
+
+    #[test]
+    fn shared_last_names_ask_which_player() {
+        let mut directory = braves();
+        directory.players.push(player(682668, "Luisangel Acuña", "Acuña", None, 2024));
+        directory.players.push(player(605141, "Mookie Betts", "Betts", None, 2022));
+        assert_eq!(candidates(directory.search(None, "acuna")).len(), 2);
+        assert_eq!(found(directory.search(None, "luisangel acuna")), Some(682668));
+        // the most recent player comes first
+        directory.players.push(player(605142, "Ronald Acuña Sr.", "Acuña", None, 2001));
+        assert_eq!(candidates(directory.search(None, "acuña")).last(), Some(&605142));
+    }
+
+    #[test]
+    fn aliases_win_over_any_match() {
+        let mut directory = braves();
+        directory.players.push(player(682668, "Luisangel Acuña", "Acuña", None, 2024));
+        let aliases = HashMap::from([("acuna".to_owned(), 660670), ("olsen".to_owned(), 518876)]);
+        assert_eq!(found(directory.search(Some(&aliases), "Acuña")), Some(660670));
+        assert_eq!(found(directory.search(Some(&aliases), "olsen")), Some(518876));
+        assert_eq!(found(directory.search(None, "olsen")), Some(621566));
+    }
+
+    #[test]
+    fn fuzzy_matches_need_a_close_score_and_a_clear_winner() {
+        // "alb" only scores 0.88 against "albies", under the 0.9 threshold
+        let mut directory = braves();
+        directory.players.push(player(645277, "Ozzie Albies", "Albies", None, 2024));
+        assert!(directory.search(None, "alb").is_none());
+        assert_eq!(found(directory.search(None, "albis")), Some(645277));
+        // kelley 0.967 and kelly 0.92 are far enough apart, kelly 0.953 and kelley 0.922 aren't
+        assert_eq!(found(directory.search(None, "kelle")), Some(641743));
+        assert_eq!(candidates(directory.search(None, "kely")), [518876, 641743]);
+    }
+
+    #[test]
+    fn failed_refreshes_back_off() {
+        let mut directory = PlayerDirectory::default();
+        assert!(directory.is_stale());
+        directory.refresh_failed();
+        assert!(!directory.is_stale());
+        directory.failed_refresh = Some(Utc::now() - Duration::minutes(FAILED_REFRESH_BACKOFF_MINUTES + 1));
+        assert!(directory.is_stale());
+    }
+}
@@ -0,0 +1,284 @@
+use std::convert::Infallible;
+use std::io::Cursor;
+
+use anyhow::{Context, Result};
+use embedded_graphics::mono_font::iso_8859_1::{FONT_10X20, FONT_7X13, FONT_7X13_BOLD};
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+use image::imageops::FilterType;
+use image::{ImageFormat, Rgb, RgbImage};
+
+use crate::cache::cache;
+use crate::http::headshot_url;
+use crate::pitch::{LivePitch, ZONE_HALF_WIDTH};
+use crate::savant::{PercentileRankings, Section};
+use crate::wpa::WpaPlay;
+
+pub const BACKGROUND: Rgb888 = Rgb888::new(0xFF, 0xFF, 0xFF);
+pub const TEXT: Rgb888 = Rgb888::new(0x1F, 0x1F, 0x1F);
+pub const MUTED: Rgb888 = Rgb888::new(0x6E, 0x6E, 0x6E);
+const TRACK: Rgb888 = Rgb888::new(0xEC, 0xEC, 0xEC);
+// savant's scale runs from blue through grey to red
+const COLD: Rgb888 = Rgb888::new(0x32, 0x5A, 0xA5);
+const NEUTRAL: Rgb888 = Rgb888::new(0xB4, 0xB4, 0xB4);
+const HOT: Rgb888 = Rgb888::new(0xD2, 0x2D, 0x49);
+
+const CARD_WIDTH: u32 = 640;
+const HEADER_HEIGHT: u32 = 132;
+const HEADSHOT_SIZE: u32 = 108;
+const SECTION_HEIGHT: u32 = 30;
+const ROW_HEIGHT: u32 = 26;
+const LABEL_WIDTH: i32 = 150;
+const BAR_WIDTH: i32 = 330;
+const MARGIN: i32 = 12;
+
+const PLOT_SIZE: u32 = 320;
+// feet above the ground that the plot covers, it's square so it spans as much either side of the plate
+const PLOT_BOTTOM: f64 = 0.5;
+const PLOT_TOP: f64 = 4.5;
+const PLATE_HALF_WIDTH: f64 = 17.0 / 24.0;
+const CHART_WIDTH: u32 = 640;
+const CHART_TOP: i32 = 48;
+const CHART_HEIGHT: i32 = 260;
+const CHART_LEFT: i32 = 52;
+const LEGEND_ROW_HEIGHT: u32 = 20;
+
+const IN_PLAY: Rgb888 = Rgb888::new(0x2E, 0x8B, 0x57);
+
+// an rgb image that embedded-graphics can draw shapes and text onto
+pub struct Canvas(pub RgbImage);
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Canvas {
+        let mut canvas = Canvas(RgbImage::new(width, height));
+        canvas.clear(BACKGROUND).unwrap_or_default();
+        canvas
+    }
+
+    pub fn fill(&mut self, top_left: Point, size: Size, color: Rgb888) {
+        Rectangle::new(top_left, size).into_styled(PrimitiveStyle::with_fill(color)).draw(self).unwrap_or_default();
+    }
+
+    pub fn text(&mut self, text: &str, position: Point, style: MonoTextStyle<'_, Rgb888>, alignment: Alignment) {
+        Text::with_text_style(text, position, style, TextStyleBuilder::new().alignment(alignment).baseline(Baseline::Middle).build()).draw(self).unwrap_or_default();
+    }
+
+    pub fn png(&self) -> Result<Vec<u8>> {
+        let mut bytes = Cursor::new(Vec::new());
+        self.0.write_to(&mut bytes, ImageFormat::Png).context("Could not encode png")?;
+        Ok(bytes.into_inner())
+    }
+}
+
+impl OriginDimensions for Canvas {
+    fn size(&self) -> Size {
+        Size::new(self.0.width(), self.0.height())
+    }
+}
+
+impl DrawTarget for Canvas {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I: IntoIterator<Item = Pixel<Rgb888>>>(&mut self, pixels: I) -> Result<(), Infallible> {
+        for Pixel(point, color) in pixels {
+            if let (Ok(x), Ok(y)) = (u32::try_from(point.x), u32::try_from(point.y)) {
+                if x < self.0.width() && y < self.0.height() {
+                    self.0.put_pixel(x, y, Rgb([color.r(), color.g(), color.b()]));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn mix(from: Rgb888, to: Rgb888, t: f64) -> Rgb888 {
+    let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    Rgb888::new(channel(from.r(), to.r()), channel(from.g(), to.g()), channel(from.b(), to.b()))
+}
+
+pub fn percentile_color(percentile: u16) -> Rgb888 {
+    let percentile = percentile.min(100) as f64 / 100.0;
+    if percentile < 0.5 { mix(COLD, NEUTRAL, percentile * 2.0) } else { mix(NEUTRAL, HOT, (percentile - 0.5) * 2.0) }
+}
+
+pub fn get_headshot(savant_player_id: usize) -> Result<RgbImage> {
+    let bytes = cache().get(&headshot_url(savant_player_id))?;
+    Ok(image::load_from_memory(&bytes).context("Could not decode headshot")?.to_rgb8())
+}
+
+pub fn render_percentile_card(name: &str, rankings: &PercentileRankings, headshot: Option<&RgbImage>) -> Result<Vec<u8>> {
+    let sections = rankings.sections().into_iter().map(|(section, _)| section).collect::<Vec<Section>>();
+    let rows = sections.iter().map(|section| rankings.section_metrics(*section).count() as u32).sum::<u32>();
+    let mut canvas = Canvas::new(CARD_WIDTH, HEADER_HEIGHT + sections.len() as u32 * SECTION_HEIGHT + rows * ROW_HEIGHT + MARGIN as u32);
+
+    let text_left = if let Some(headshot) = headshot {
+        let headshot = image::imageops::resize(headshot, HEADSHOT_SIZE, HEADSHOT_SIZE, FilterType::Triangle);
+        image::imageops::overlay(&mut canvas.0, &headshot, MARGIN as i64, MARGIN as i64);
+        MARGIN * 2 + HEADSHOT_SIZE as i32
+    } else {
+        MARGIN
+    };
+    canvas.text(name, Point::new(text_left, 44), MonoTextStyle::new(&FONT_10X20, TEXT), Alignment::Left);
+    canvas.text(&format!("{} Percentile Rankings", rankings.year), Point::new(text_left, 76), MonoTextStyle::new(&FONT_7X13, MUTED), Alignment::Left);
+    canvas.fill(Point::new(MARGIN, HEADER_HEIGHT as i32 - 4), Size::new(CARD_WIDTH - MARGIN as u32 * 2, 1), TRACK);
+
+    let mut y = HEADER_HEIGHT as i32;
+    for section in sections {
+        canvas.text(section.name(), Point::new(MARGIN, y + SECTION_HEIGHT as i32 / 2), MonoTextStyle::new(&FONT_7X13_BOLD, TEXT), Alignment::Left);
+        y += SECTION_HEIGHT as i32;
+        for metric in rankings.section_metrics(section) {
+            let middle = y + ROW_HEIGHT as i32 / 2;
+            let bar_left = MARGIN + LABEL_WIDTH;
+            canvas.text(&metric.label, Point::new(bar_left - MARGIN, middle), MonoTextStyle::new(&FONT_7X13, TEXT), Alignment::Right);
+            canvas.fill(Point::new(bar_left, middle - 3), Size::new(BAR_WIDTH as u32, 6), TRACK);
+            if let Some(percentile) = metric.percentile {
+                let color = percentile_color(percentile);
+                let bar_end = bar_left + BAR_WIDTH * percentile.min(100) as i32 / 100;
+                canvas.fill(Point::new(bar_left, middle - 3), Size::new((bar_end - bar_left) as u32, 6), color);
+                Circle::with_center(Point::new(bar_end, middle), ROW_HEIGHT - 4).into_styled(PrimitiveStyle::with_fill(color)).draw(&mut canvas).unwrap_or_default();
+                canvas.text(&percentile.to_string(), Point::new(bar_end + 1, middle), MonoTextStyle::new(&FONT_7X13_BOLD, BACKGROUND), Alignment::Center);
+            }
+            if let Some(value) = &metric.value {
+                canvas.text(value, Point::new(CARD_WIDTH as i32 - MARGIN, middle), MonoTextStyle::new(&FONT_7X13, MUTED), Alignment::Right);
+            }
+            y += ROW_HEIGHT as i32;
+        }
+    }
+    canvas.png()
+}
+
+// the pitches of one at bat over the strike zone from the catcher's view, numbered in order
+pub fn render_zone_plot(pitches: &[&LivePitch]) -> Result<Vec<u8>> {
+    let mut canvas = Canvas::new(PLOT_SIZE, PLOT_SIZE);
+    let scale = PLOT_SIZE as f64 / (PLOT_TOP - PLOT_BOTTOM);
+    let point = |x: f64, z: f64| Point::new((PLOT_SIZE as f64 / 2.0 + x * scale).round() as i32, ((PLOT_TOP - z) * scale).round() as i32);
+
+    let last = pitches.last().context("Could not get the at bat's pitches")?;
+    let (top, bottom) = (last.zone_top.unwrap_or(3.5), last.zone_bottom.unwrap_or(1.5));
+    let top_left = point(-PLATE_HALF_WIDTH, top);
+    let bottom_right = point(PLATE_HALF_WIDTH, bottom);
+    Rectangle::with_corners(top_left, bottom_right).into_styled(PrimitiveStyle::with_stroke(TEXT, 2)).draw(&mut canvas).unwrap_or_default();
+    for third in 1..3 {
+        let x = -PLATE_HALF_WIDTH + PLATE_HALF_WIDTH * 2.0 / 3.0 * third as f64;
+        let z = bottom + (top - bottom) / 3.0 * third as f64;
+        canvas.fill(Point::new(point(x, 0.0).x, top_left.y), Size::new(1, (bottom_right.y - top_left.y) as u32), TRACK);
+        canvas.fill(Point::new(top_left.x, point(0.0, z).y), Size::new((bottom_right.x - top_left.x) as u32, 1), TRACK);
+    }
+    let plate = point(0.0, PLOT_BOTTOM + 0.15);
+    canvas.fill(Point::new(point(-PLATE_HALF_WIDTH, 0.0).x, plate.y), Size::new((bottom_right.x - top_left.x) as u32, 4), MUTED);
+    canvas.text(&format!("{} vs. {}", last.pitcher_name, last.batter_name), Point::new(MARGIN, MARGIN), MonoTextStyle::new(&FONT_7X13, MUTED), Alignment::Left);
+    canvas.text("Catcher's view", Point::new(MARGIN, MARGIN + 16), MonoTextStyle::new(&FONT_7X13, MUTED), Alignment::Left);
+
+    let diameter = (ZONE_HALF_WIDTH - PLATE_HALF_WIDTH) * 2.0 * scale * 1.5;
+    for (number, pitch) in pitches.iter().enumerate() {
+        let (Some(x), Some(z)) = (pitch.x, pitch.z) else { continue };
+        let color = match pitch.call.as_str() {
+            "B" => COLD,
+            "X" => IN_PLAY,
+            _ => HOT,
+        };
+        let center = point(x, z);
+        Circle::with_center(center, diameter.round() as u32).into_styled(PrimitiveStyle::with_fill(color)).draw(&mut canvas).unwrap_or_default();
+        canvas.text(&(number + 1).to_string(), center + Point::new(1, 0), MonoTextStyle::new(&FONT_7X13_BOLD, BACKGROUND), Alignment::Center);
+    }
+    canvas.png()
+}
+
+// home win probability after every play, starting from an even game, with `swings` numbered on the line and listed below
+pub fn render_win_probability(title: &str, home: &str, away: &str, plays: &[WpaPlay], swings: &[usize]) -> Result<Vec<u8>> {
+    let mut canvas = Canvas::new(CHART_WIDTH, (CHART_TOP + CHART_HEIGHT) as u32 + MARGIN as u32 * 2 + swings.len() as u32 * LEGEND_ROW_HEIGHT);
+    let right = CHART_WIDTH as i32 - MARGIN;
+    let point = |idx: usize, probability: f64| Point::new(CHART_LEFT + ((right - CHART_LEFT) as f64 * idx as f64 / plays.len().max(1) as f64).round() as i32, CHART_TOP + (CHART_HEIGHT as f64 * (1.0 - probability.clamp(0.0, 100.0) / 100.0)).round() as i32);
+
+    canvas.text(title, Point::new(MARGIN, MARGIN + 6), MonoTextStyle::new(&FONT_10X20, TEXT), Alignment::Left);
+    for (probability, label) in [(100.0, "100%"), (50.0, "50%"), (0.0, "0%")] {
+        let y = point(0, probability).y;
+        canvas.fill(Point::new(CHART_LEFT, y), Size::new((right - CHART_LEFT) as u32, 1), TRACK);
+        canvas.text(label, Point::new(CHART_LEFT - 6, y), MonoTextStyle::new(&FONT_7X13, MUTED), Alignment::Right);
+    }
+    canvas.text(home, Point::new(right - 4, point(0, 90.0).y), MonoTextStyle::new(&FONT_7X13_BOLD, HOT), Alignment::Right);
+    canvas.text(away, Point::new(right - 4, point(0, 10.0).y), MonoTextStyle::new(&FONT_7X13_BOLD, COLD), Alignment::Right);
+
+    let mut previous = point(0, 50.0);
+    for (idx, play) in plays.iter().enumerate() {
+        let next = point(idx + 1, play.home_win_probability);
+        Line::new(previous, next).into_styled(PrimitiveStyle::with_stroke(TEXT, 2)).draw(&mut canvas).unwrap_or_default();
+        previous = next;
+    }
+
+    let mut legend_y = CHART_TOP + CHART_HEIGHT + MARGIN * 2;
+    for (number, &idx) in swings.iter().enumerate() {
+        let play = &plays[idx];
+        let color = if play.home_added >= 0.0 { HOT } else { COLD };
+        let center = point(idx + 1, play.home_win_probability);
+        Circle::with_center(center, 18).into_styled(PrimitiveStyle::with_fill(color)).draw(&mut canvas).unwrap_or_default();
+        canvas.text(&(number + 1).to_string(), center + Point::new(1, 0), MonoTextStyle::new(&FONT_7X13_BOLD, BACKGROUND), Alignment::Center);
+
+        let line = format!("{}. {} +{:.1}% {}", number + 1, if play.home_added >= 0.0 { home } else { away }, play.home_added.abs(), play.summary());
+        let line = line.chars().take((CHART_WIDTH as i32 - MARGIN * 2) as usize / 7).collect::<String>();
+        canvas.text(&line, Point::new(MARGIN, legend_y), MonoTextStyle::new(&FONT_7X13, color), Alignment::Left);
+        legend_y += LEGEND_ROW_HEIGHT as i32;
+    }
+    canvas.png()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::savant::PercentileMetric;
+
+    fn metric(id: &str, section: Section, percentile: Option<u16>, value: Option<&str>) -> PercentileMetric {
+        PercentileMetric { id: id.to_owned(), label: id.to_owned(), section, percentile, value: value.map(str::to_owned) }
+    }
+
+    #[test]
+    fn percentile_colors_run_from_blue_to_red() {
+        assert_eq!(percentile_color(0), COLD);
+        assert_eq!(percentile_color(50), NEUTRAL);
+        assert_eq!(percentile_color(100), HOT);
+        assert!(percentile_color(90).r() > percentile_color(60).r());
+    }
+
+    #[test]
+    fn renders_a_row_for_every_shown_metric() {
+        let season = PercentileRankings {
+            year: 2023,
+            metrics: vec![
+                metric("xwOBA", Section::Batting, Some(100), Some(".441")),
+                metric("Whiff %", Section::Batting, Some(96), Some("12.8%")),
+                metric("Arm Strength", Section::Fielding, Some(86), None),
+                metric("Sprint Speed", Section::Baserunning, None, None),
+            ],
+        };
+        let png = render_percentile_card("Ronald Acuña Jr.", &season, Some(&RgbImage::new(60, 60))).unwrap();
+        let card = image::load_from_memory(&png).unwrap();
+        assert_eq!(card.width(), CARD_WIDTH);
+        assert_eq!(card.height(), HEADER_HEIGHT + 2 * SECTION_HEIGHT + 3 * ROW_HEIGHT + MARGIN as u32);
+    }
+
+    #[test]
+    fn zone_plot_needs_the_at_bat() {
+        assert!(render_zone_plot(&[]).is_err());
+        let game = serde_json::json!({ "team_home": [
+            { "game_total_pitches": 1, "ab_number": 1, "batter_name": "Matt Olson", "pitcher_name": "Zack Wheeler", "px": -0.9, "pz": 2.4, "sz_top": 3.4, "sz_bot": 1.6, "call": "B" },
+            { "game_total_pitches": 2, "ab_number": 1, "batter_name": "Matt Olson", "pitcher_name": "Zack Wheeler", "px": 0.3, "pz": 3.0, "sz_top": 3.4, "sz_bot": 1.6, "call": "S" },
+            { "game_total_pitches": 3, "ab_number": 1, "batter_name": "Matt Olson", "pitcher_name": "Zack Wheeler", "px": 0.1, "pz": 1.9, "sz_top": 3.4, "sz_bot": 1.6, "call": "X" },
+        ] });
+        let pitches = crate::pitch::get_game_pitches(&game);
+        let png = render_zone_plot(&pitches.iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(image::load_from_memory(&png).unwrap().width(), PLOT_SIZE);
+    }
+
+    #[test]
+    fn win_probability_chart_lists_every_swing() {
+        let play = |home_win_probability: f64, home_added: f64| WpaPlay { at_bat: 1, inning: Some(9), home_batting: Some(true), batter_name: None, pitcher_name: None, description: Some("Austin Riley doubles (31) on a line drive to left fielder Kyle Schwarber.".to_owned()), home_win_probability, home_added };
+        let plays = [play(45.0, -5.0), play(38.0, -7.0), play(71.0, 33.0), play(62.0, -9.0), play(100.0, 38.0)];
+        let png = render_win_probability("Phillies @ Braves", "Braves", "Phillies", &plays, &[4, 2, 3]).unwrap();
+        let chart = image::load_from_memory(&png).unwrap();
+        assert_eq!(chart.height(), (CHART_TOP + CHART_HEIGHT) as u32 + MARGIN as u32 * 2 + 3 * LEGEND_ROW_HEIGHT);
+    }
+}
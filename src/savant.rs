@@ -0,0 +1,660 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+
+use crate::cache::cache;
+use crate::config::config;
+use crate::get_with_sleep;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Section {
+    Batting,
+    Fielding,
+    Baserunning,
+    Pitching,
+}
+
+impl Section {
+    pub fn name(self) -> &'static str {
+        match self {
+            Section::Batting => "Batting",
+            Section::Fielding => "Fielding",
+            Section::Baserunning => "Baserunning",
+            Section::Pitching => "Pitching",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Section::Batting => ":cricket_game: Batting",
+            Section::Fielding => ":gloves: Fielding",
+            Section::Baserunning => ":athletic_shoe: Baserunning",
+            Section::Pitching => ":baseball: Pitching",
+        }
+    }
+}
+
+pub const SECTIONS: [Section; 4] = [Section::Batting, Section::Fielding, Section::Baserunning, Section::Pitching];
+
+#[derive(Copy, Clone)]
+enum ValueFormat {
+    // batting average style, ".412"
+    Rate,
+    Decimal(&'static str),
+    Percent,
+    Integer,
+}
+
+impl ValueFormat {
+    fn format(self, value: f64) -> String {
+        match self {
+            ValueFormat::Rate => format!("{value:.3}").trim_start_matches('0').to_owned(),
+            ValueFormat::Decimal(unit) => format!("{value:.1}{unit}"),
+            ValueFormat::Percent => format!("{value:.1}%"),
+            ValueFormat::Integer => format!("{value:.0}"),
+        }
+    }
+}
+
+// display metadata for the columns we know about, in the order they're shown
+struct MetricInfo {
+    id: &'static str,
+    // normalized header text of every name savant has used for this column
+    headers: &'static [&'static str],
+    label: &'static str,
+    section: Section,
+    // column of the custom leaderboard csv holding the underlying season value
+    value: Option<(&'static str, ValueFormat)>,
+}
+
+const fn metric(id: &'static str, headers: &'static [&'static str], label: &'static str, section: Section) -> MetricInfo {
+    MetricInfo { id, headers, label, section, value: None }
+}
+
+impl MetricInfo {
+    const fn value(self, column: &'static str, format: ValueFormat) -> MetricInfo {
+        MetricInfo { value: Some((column, format)), ..self }
+    }
+}
+
+const HITTING_METRICS: &[MetricInfo] = &[
+    metric("xwoba", &["xwoba"], "xwOBA", Section::Batting).value("xwoba", ValueFormat::Rate),
+    metric("xba", &["xba"], "xBA", Section::Batting).value("xba", ValueFormat::Rate),
+    metric("xslg", &["xslg"], "xSLG", Section::Batting).value("xslg", ValueFormat::Rate),
+    metric("xiso", &["xiso"], "xISO", Section::Batting).value("xiso", ValueFormat::Rate),
+    metric("xobp", &["xobp"], "xOBP", Section::Batting).value("xobp", ValueFormat::Rate),
+    metric("avg_ev", &["ev"], "Avg EV", Section::Batting).value("exit_velocity_avg", ValueFormat::Decimal(" mph")),
+    metric("max_ev", &["max ev"], "Max EV", Section::Batting),
+    metric("bat_speed", &["bat speed"], "Bat Speed", Section::Batting).value("avg_swing_speed", ValueFormat::Decimal(" mph")),
+    metric("swing_length", &["swing length"], "Swing Length", Section::Batting).value("avg_swing_length", ValueFormat::Decimal(" ft")),
+    metric("barrels", &["brl"], "Barrels", Section::Batting).value("barrel", ValueFormat::Integer),
+    metric("barrel_pct", &["brl%"], "Barrel %", Section::Batting).value("barrel_batted_rate", ValueFormat::Percent),
+    metric("hard_hit_pct", &["hard hit%"], "Hard-Hit %", Section::Batting).value("hard_hit_percent", ValueFormat::Percent),
+    metric("chase_pct", &["chase rate"], "Chase %", Section::Batting).value("oz_swing_percent", ValueFormat::Percent),
+    metric("whiff_pct", &["whiff%"], "Whiff %", Section::Batting).value("whiff_percent", ValueFormat::Percent),
+    metric("k_pct", &["k%"], "K %", Section::Batting).value("k_percent", ValueFormat::Percent),
+    metric("bb_pct", &["bb%"], "BB %", Section::Batting).value("bb_percent", ValueFormat::Percent),
+    metric("oaa", &["oaa"], "Range (OAA)", Section::Fielding),
+    metric("arm_strength", &["arm strength"], "Arm Strength", Section::Fielding),
+    metric("sprint_speed", &["speed"], "Sprint Speed", Section::Baserunning).value("sprint_speed", ValueFormat::Decimal(" ft/s")),
+];
+
+const PITCHING_METRICS: &[MetricInfo] = &[
+    metric("xera", &["xera", "xwoba xera"], "xERA", Section::Pitching).value("xera", ValueFormat::Decimal("")),
+    metric("xwoba", &["xwoba"], "xwOBA", Section::Pitching).value("xwoba", ValueFormat::Rate),
+    metric("xba", &["xba"], "xBA", Section::Pitching).value("xba", ValueFormat::Rate),
+    metric("xslg", &["xslg"], "xSLG", Section::Pitching).value("xslg", ValueFormat::Rate),
+    metric("xiso", &["xiso"], "xISO", Section::Pitching).value("xiso", ValueFormat::Rate),
+    metric("xobp", &["xobp"], "xOBP", Section::Pitching).value("xobp", ValueFormat::Rate),
+    metric("fb_velo", &["fb velo"], "Fastball Velo", Section::Pitching).value("fastball_avg_speed", ValueFormat::Decimal(" mph")),
+    metric("fb_spin", &["fb spin"], "Fastball Spin", Section::Pitching).value("fastball_avg_spin", ValueFormat::Integer),
+    metric("cb_spin", &["cb spin"], "Curve Spin", Section::Pitching).value("breaking_avg_spin", ValueFormat::Integer),
+    metric("avg_ev", &["ev"], "Avg EV", Section::Pitching).value("exit_velocity_avg", ValueFormat::Decimal(" mph")),
+    metric("max_ev", &["max ev"], "Max EV", Section::Pitching),
+    metric("chase_pct", &["chase rate"], "Chase %", Section::Pitching).value("oz_swing_percent", ValueFormat::Percent),
+    metric("whiff_pct", &["whiff%"], "Whiff %", Section::Pitching).value("whiff_percent", ValueFormat::Percent),
+    metric("k_pct", &["k%"], "K %", Section::Pitching).value("k_percent", ValueFormat::Percent),
+    metric("bb_pct", &["bb%"], "BB %", Section::Pitching).value("bb_percent", ValueFormat::Percent),
+    metric("barrels", &["brl"], "Barrels", Section::Pitching).value("barrel", ValueFormat::Integer),
+    metric("barrel_pct", &["brl%"], "Barrel %", Section::Pitching).value("barrel_batted_rate", ValueFormat::Percent),
+    metric("hard_hit_pct", &["hard hit%"], "Hard-Hit %", Section::Pitching).value("hard_hit_percent", ValueFormat::Percent),
+    metric("extension", &["extension"], "Extension", Section::Pitching).value("release_extension", ValueFormat::Decimal(" ft")),
+];
+
+pub struct PercentileMetric {
+    // stable across seasons, unknown columns use their normalized header
+    pub id: String,
+    pub label: String,
+    pub section: Section,
+    pub percentile: Option<u16>,
+    // the formatted season value behind the percentile, once filled in by `fill_metric_values`
+    pub value: Option<String>,
+}
+
+// every metric savant published for a season, hitting and pitching tables alike, in display order
+pub struct PercentileRankings {
+    pub year: u16,
+    pub metrics: Vec<PercentileMetric>,
+}
+
+pub type Metrics<'a> = Vec<(&'a str, Option<u16>)>;
+
+impl PercentileRankings {
+    fn has(&self, section: Section) -> bool {
+        self.metrics.iter().any(|metric| metric.section == section && metric.percentile.is_some())
+    }
+
+    pub fn hitter(&self) -> bool {
+        self.has(Section::Batting)
+    }
+
+    pub fn pitcher(&self) -> bool {
+        self.has(Section::Pitching)
+    }
+
+    pub fn percentile(&self, section: Section, id: &str) -> Option<u16> {
+        self.metrics.iter().find(|metric| metric.section == section && metric.id == id)?.percentile
+    }
+
+    pub fn section_metrics(&self, section: Section) -> impl Iterator<Item = &PercentileMetric> {
+        self.metrics.iter().filter(move |metric| metric.section == section)
+    }
+
+    pub fn section(&self, section: Section) -> Metrics<'_> {
+        self.section_metrics(section).map(|metric| (metric.label.as_str(), metric.percentile)).collect()
+    }
+
+    pub fn sections(&self) -> Vec<(Section, Metrics<'_>)> {
+        SECTIONS.into_iter().filter(|section| self.has(*section)).map(|section| (section, self.section(section))).collect()
+    }
+}
+
+pub struct PlayerPercentiles {
+    pub name: String,
+    // ordered from the oldest season to the most recent one
+    pub seasons: Vec<PercentileRankings>,
+    // normalized headers of columns Savant added that are only shown with a generated label
+    pub unknown_headers: Vec<String>,
+}
+
+impl PlayerPercentiles {
+    pub fn season(&self, year: u16) -> Option<&PercentileRankings> {
+        self.seasons.iter().find(|season| season.year == year)
+    }
+
+    pub fn latest(&self) -> Option<&PercentileRankings> {
+        self.seasons.last()
+    }
+
+    pub fn season_mut(&mut self, year: Option<u16>) -> Option<&mut PercentileRankings> {
+        match year {
+            Some(year) => self.seasons.iter_mut().find(|season| season.year == year),
+            None => self.seasons.last_mut(),
+        }
+    }
+}
+
+pub struct PlayerCandidate {
+    pub id: usize,
+    pub name: String,
+    pub position: Option<String>,
+    pub team: Option<String>,
+    pub first_year: Option<u64>,
+    pub last_year: Option<u64>,
+}
+
+impl PlayerCandidate {
+    fn from_search(result: &Value) -> Option<PlayerCandidate> {
+        let field = |key: &str| result[key].as_str().filter(|value| !value.is_empty()).map(str::to_owned);
+        let year = |key: &str| result[key].as_u64().or_else(|| result[key].as_str()?.parse().ok());
+        Some(PlayerCandidate {
+            id: result["id"].as_str()?.parse().ok()?,
+            name: field("name_display_first_last").or_else(|| field("name"))?,
+            position: field("pos"),
+            team: field("name_display_club").or_else(|| field("team")),
+            first_year: year("firstYear"),
+            last_year: year("lastYear"),
+        })
+    }
+
+    // savant lists names as "Last, First", so both orders count as an exact match
+    fn is_exact_match(&self, search: &str) -> bool {
+        let first_last = match self.name.split_once(", ") {
+            Some((last, first)) => format!("{first} {last}"),
+            None => self.name.clone(),
+        };
+        self.name.to_lowercase() == search.trim().to_lowercase() || first_last.to_lowercase() == search.trim().to_lowercase()
+    }
+
+    pub fn summary(&self) -> String {
+        let years = match (self.first_year, self.last_year) {
+            (Some(first_year), Some(last_year)) if first_year != last_year => Some(format!("{first_year}-{last_year}")),
+            (Some(year), _) | (_, Some(year)) => Some(year.to_string()),
+            (None, None) => None,
+        };
+        [self.position.clone(), self.team.clone(), years].into_iter().flatten().collect::<Vec<_>>().join(" · ")
+    }
+}
+
+pub enum PlayerSearch {
+    Found(usize),
+    Candidates(Vec<PlayerCandidate>),
+    NotFound,
+}
+
+pub fn search_players(search: &str) -> Result<PlayerSearch> {
+    if let Ok(id) = search.trim().parse::<usize>() {
+        return Ok(PlayerSearch::Found(id))
+    }
+    Ok(parse_player_search(search, &get_with_sleep(&format!("{}/player/search-all?search={search}", config().savant_url))?))
+}
+
+fn parse_player_search(search: &str, results: &Value) -> PlayerSearch {
+    let candidates = results.as_array().into_iter().flatten().filter_map(PlayerCandidate::from_search).collect::<Vec<_>>();
+    let mut exact_matches = candidates.iter().filter(|candidate| candidate.is_exact_match(search));
+    if let (Some(candidate), None) = (exact_matches.next(), exact_matches.next()) {
+        return PlayerSearch::Found(candidate.id)
+    }
+    match &*candidates {
+        [] => PlayerSearch::NotFound,
+        [candidate] => PlayerSearch::Found(candidate.id),
+        _ => PlayerSearch::Candidates(candidates),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PercentileParseError {
+    MissingPlayerName,
+    MissingHeaders,
+    MissingYearColumn,
+    InvalidYear(String),
+}
+
+impl std::fmt::Display for PercentileParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PercentileParseError::MissingPlayerName => write!(f, "Savant player page has no player name"),
+            PercentileParseError::MissingHeaders => write!(f, "Savant percentile table has no header row"),
+            PercentileParseError::MissingYearColumn => write!(f, "Savant percentile table has no year column"),
+            PercentileParseError::InvalidYear(year) => write!(f, "Savant percentile table has an invalid year: {year}"),
+        }
+    }
+}
+
+impl std::error::Error for PercentileParseError {}
+
+// text content with collapsed whitespace, lowercased so "Hard <br>Hit%" and "hard  hit%" are both "hard hit%",
+// with punctuation other than % read as a space so "Hard-Hit<br/>%" is "hard hit%" too
+fn header_text(header: ElementRef) -> (String, String) {
+    let text = header.text().flat_map(str::split_whitespace).collect::<Vec<_>>().join(" ");
+    let normalized = text.to_lowercase().chars().map(|c| if c.is_alphanumeric() || c == '%' { c } else { ' ' }).collect::<String>();
+    (normalized.split_whitespace().collect::<Vec<_>>().join(" ").replace(" %", "%"), text)
+}
+
+struct PercentileTable {
+    name: String,
+    // every column as (normalized header, header text as displayed)
+    headers: Vec<(String, String)>,
+    // every season row with the percentile under each header
+    rows: Vec<(u16, Vec<Option<u16>>)>,
+}
+
+impl PercentileTable {
+    fn is_pitching(&self) -> bool {
+        self.headers.iter().any(|(header, _)| header.contains("xera"))
+    }
+}
+
+pub const PLAYER_PAGE_STATS: [&str; 2] = ["statcast-r-hitting-mlb", "statcast-r-pitching-mlb"];
+
+pub fn get_player_page(savant_player_id: usize, stats: &str) -> Result<String> {
+    Ok(String::from_utf8_lossy(&cache().get(&format!("{}/savant-player/{savant_player_id}?stats={stats}", config().savant_url))?).into_owned())
+}
+
+// saves the raw player pages so the percentile parser can be tested against them offline, next to
+// but never over the hand-trimmed synthetic-* fixtures the tests use
+pub fn capture_fixture(savant_player_id: usize, directory: &Path) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(directory)?;
+    let mut paths = Vec::new();
+    for stats in PLAYER_PAGE_STATS {
+        let path = directory.join(format!("savant-player-{savant_player_id}-{stats}.html"));
+        std::fs::write(&path, get_player_page(savant_player_id, stats)?)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+fn get_percentile_table(savant_player_id: usize, stats: &str) -> Result<Option<PercentileTable>> {
+    Ok(parse_percentile_table(&get_player_page(savant_player_id, stats)?)?)
+}
+
+// `Ok(None)` when the page has no percentile table at all, e.g. for players without enough playing time
+fn parse_percentile_table(page: &str) -> std::result::Result<Option<PercentileTable>, PercentileParseError> {
+    let html = Html::parse_document(page);
+    let Some(table) = html.select(&Selector::parse("table#percentileRankings").unwrap()).next() else { return Ok(None) };
+    let name = html
+        .select(&Selector::parse(".bio-player-name").unwrap())
+        .next()
+        .map(|name| name.text().flat_map(str::split_whitespace).collect::<Vec<_>>().join(" "))
+        .filter(|name| !name.is_empty())
+        .ok_or(PercentileParseError::MissingPlayerName)?;
+    let headers = table.select(&Selector::parse("thead tr").unwrap()).next().ok_or(PercentileParseError::MissingHeaders)?.select(&Selector::parse("th, td").unwrap()).map(header_text).collect::<Vec<_>>();
+    let year_column = headers.iter().position(|(header, _)| header == "year" || header == "season").ok_or(PercentileParseError::MissingYearColumn)?;
+    let cell_selector = Selector::parse("td, th").unwrap();
+    let mut rows = Vec::new();
+    for row in table.select(&Selector::parse("tbody tr").unwrap()) {
+        let cells = row.select(&cell_selector).map(|cell| cell.text().collect::<String>().trim().to_owned()).collect::<Vec<_>>();
+        let Some(year) = cells.get(year_column).filter(|year| !year.is_empty()) else { continue };
+        let year = year.parse::<u16>().map_err(|_| PercentileParseError::InvalidYear(year.clone()))?;
+        rows.push((year, (0..headers.len()).map(|idx| if idx == year_column { None } else { cells.get(idx).and_then(|cell| cell.parse::<u16>().ok()) }).collect()));
+    }
+    drop(html);
+    Ok(Some(PercentileTable { name, headers, rows }))
+}
+
+pub fn get_percentile_rankings(savant_player_id: usize) -> Result<Option<PlayerPercentiles>> {
+    let mut tables = Vec::new();
+    for stats in PLAYER_PAGE_STATS {
+        tables.extend(get_percentile_table(savant_player_id, stats)?);
+    }
+    Ok(merge_percentile_tables(tables))
+}
+
+fn merge_percentile_tables(tables: Vec<PercentileTable>) -> Option<PlayerPercentiles> {
+    let name = tables.first()?.name.clone();
+    let mut seasons = Vec::<PercentileRankings>::new();
+    let mut unknown_headers = Vec::new();
+    // a pitcher's hitting page can fall back to the pitching table, so classify each table by its columns rather than by the page it came from
+    let (mut hitting_seen, mut pitching_seen) = (false, false);
+    for table in tables {
+        let is_pitching = table.is_pitching();
+        let seen = if is_pitching { &mut pitching_seen } else { &mut hitting_seen };
+        if *seen {
+            continue
+        }
+        *seen = true;
+
+        let known_metrics = if is_pitching { PITCHING_METRICS } else { HITTING_METRICS };
+        let default_section = if is_pitching { Section::Pitching } else { Section::Batting };
+        // (display position, column, id, label, section) for every column but the year
+        let mut columns = Vec::new();
+        for (column, (header, text)) in table.headers.iter().enumerate() {
+            if header == "year" || header == "season" {
+                continue
+            }
+            match known_metrics.iter().position(|metric| metric.headers.contains(&header.as_str())) {
+                Some(position) => columns.push((position, column, known_metrics[position].id.to_owned(), known_metrics[position].label.to_owned(), known_metrics[position].section)),
+                None => {
+                    if !unknown_headers.contains(header) {
+                        unknown_headers.push(header.clone());
+                    }
+                    columns.push((known_metrics.len() + column, column, header.clone(), text.clone(), default_section));
+                }
+            }
+        }
+        columns.sort_by_key(|(position, ..)| *position);
+
+        for (year, row) in &table.rows {
+            let idx = match seasons.iter().position(|season| season.year == *year) {
+                Some(idx) => idx,
+                None => {
+                    seasons.push(PercentileRankings { year: *year, metrics: Vec::new() });
+                    seasons.len() - 1
+                }
+            };
+            seasons[idx].metrics.extend(columns.iter().map(|(_, column, id, label, section)| PercentileMetric { id: id.clone(), label: label.clone(), section: *section, percentile: row.get(*column).copied().flatten(), value: None }));
+        }
+    }
+    for season in &mut seasons {
+        season.metrics.sort_by_key(|metric| SECTIONS.iter().position(|section| *section == metric.section));
+    }
+    seasons.sort_by_key(|season| season.year);
+    Some(PlayerPercentiles { name, seasons, unknown_headers })
+}
+
+pub type LeaderboardRow = HashMap<String, String>;
+
+// the rows of a savant leaderboard csv that belong to one player, keyed by column header
+pub fn get_leaderboard_rows(url: &str, player_column: &str, savant_player_id: usize) -> Result<Vec<LeaderboardRow>> {
+    let csv = String::from_utf8_lossy(&cache().get(url)?).into_owned();
+    let mut reader = csv::Reader::from_reader(csv.trim_start_matches('\u{feff}').as_bytes());
+    let headers = reader.headers()?.clone();
+    Ok(reader
+        .records()
+        .flatten()
+        .map(|record| headers.iter().zip(record.iter()).map(|(header, value)| (header.to_owned(), value.to_owned())).collect::<LeaderboardRow>())
+        .filter(|row| row.get(player_column).and_then(|id| id.parse::<usize>().ok()) == Some(savant_player_id))
+        .collect())
+}
+
+// reads the season values behind the percentiles from savant's custom leaderboard csv
+pub fn fill_metric_values(savant_player_id: usize, rankings: &mut PercentileRankings) -> Result<()> {
+    for (kind, section_metrics) in [("batter", HITTING_METRICS), ("pitcher", PITCHING_METRICS)] {
+        let is_pitching = kind == "pitcher";
+        if rankings.metrics.iter().all(|metric| (metric.section == Section::Pitching) != is_pitching) {
+            continue
+        }
+        let selections = section_metrics.iter().filter_map(|metric| metric.value.map(|(column, _)| column)).collect::<Vec<_>>();
+        let rows = get_leaderboard_rows(&format!("{}/leaderboard/custom?year={year}&type={kind}&min=1&selections={selections}&csv=true", config().savant_url, year = rankings.year, selections = selections.join(",")), "player_id", savant_player_id)?;
+        let Some(row) = rows.first() else { continue };
+        for metric in rankings.metrics.iter_mut().filter(|metric| (metric.section == Section::Pitching) == is_pitching) {
+            let Some((column, format)) = section_metrics.iter().find(|info| info.id == metric.id).and_then(|info| info.value) else { continue };
+            metric.value = row.get(column).and_then(|value| value.parse::<f64>().ok()).map(|value| format.format(value));
+        }
+    }
+    Ok(())
+}
+
+const PERCENTILE_WIDTH: usize = 15;
+
+fn percentile_line(percentile: u16) -> String {
+    "-".repeat((percentile as usize * PERCENTILE_WIDTH + 50) / 100)
+}
+
+pub fn format_percentile_ranking(name: &str, ranking: Option<u16>, value: Option<&str>) -> String {
+    if let Some(percentile) = ranking {
+        let percentile_surroundings = if percentile >= 95 { "***" } else if percentile >= 90 { "**" } else { "" };
+        let value = value.map(|value| format!(" {value}")).unwrap_or_default();
+        format!("\n`{percentile: >3}% / [{percentile_line: <PERCENTILE_WIDTH$}]` {percentile_surroundings}{name}{percentile_surroundings}{value}", percentile_line = percentile_line(percentile))
+    } else {
+        String::new()
+    }
+}
+
+// the first player's bar grows to the left and the second player's to the right so the two meet in the middle
+pub fn format_percentile_comparison(name: &str, first: Option<u16>, second: Option<u16>) -> String {
+    if let (Some(first), Some(second)) = (first, second) {
+        let difference = first as i32 - second as i32;
+        let name = if difference >= 10 { format!("<< {name}") } else if difference <= -10 { format!("{name} >>") } else { name.to_owned() };
+        format!("\n`{first: >3}% [{first_line: >PERCENTILE_WIDTH$}|{second_line: <PERCENTILE_WIDTH$}] {second: >3}% {difference: >+4}` {name}", first_line = percentile_line(first), second_line = percentile_line(second))
+    } else {
+        String::new()
+    }
+}
+
+pub fn format_percentile_trend(name: &str, rankings: &[Option<u16>]) -> Option<String> {
+    if rankings.iter().all(Option::is_none) {
+        return None
+    }
+    let mut previous = None;
+    let cells = rankings
+        .iter()
+        .map(|&ranking| {
+            let cell = match (previous, ranking) {
+                (Some(previous), Some(percentile)) if percentile > previous => format!("{percentile: >3}▲"),
+                (Some(previous), Some(percentile)) if percentile < previous => format!("{percentile: >3}▼"),
+                (_, Some(percentile)) => format!("{percentile: >3} "),
+                (_, None) => "  - ".to_owned(),
+            };
+            previous = ranking.or(previous);
+            cell
+        })
+        .collect::<Vec<_>>();
+    Some(format!("{name: <13}{}", cells.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_fixture(page: &str) -> PlayerPercentiles {
+        merge_percentile_tables(parse_percentile_table(page).unwrap().into_iter().collect()).unwrap()
+    }
+
+    #[test]
+    fn parses_every_season_of_a_hitter() {
+        let player = parse_fixture(include_str!("../fixtures/synthetic-savant-hitter-660670.html"));
+        assert_eq!(player.name, "Ronald Acuña Jr.");
+        assert_eq!(player.seasons.iter().map(|season| season.year).collect::<Vec<_>>(), [2022, 2023, 2024]);
+        assert!(player.unknown_headers.is_empty());
+
+        let season = player.season(2023).unwrap();
+        assert!(season.hitter() && season.has(Section::Fielding) && season.has(Section::Baserunning) && !season.pitcher());
+        assert_eq!(season.percentile(Section::Batting, "xwoba"), Some(100));
+        assert_eq!(season.percentile(Section::Batting, "hard_hit_pct"), Some(99));
+        assert_eq!(season.percentile(Section::Batting, "chase_pct"), Some(79));
+        assert_eq!(season.percentile(Section::Fielding, "arm_strength"), Some(89));
+        assert_eq!(season.percentile(Section::Batting, "bat_speed"), None);
+        assert_eq!(player.latest().unwrap().percentile(Section::Batting, "bat_speed"), Some(78));
+    }
+
+    #[test]
+    fn keeps_every_published_column() {
+        let player = parse_fixture(include_str!("../fixtures/synthetic-savant-hitter-660670.html"));
+        let season = player.latest().unwrap();
+        assert_eq!(season.percentile(Section::Batting, "xiso"), Some(67));
+        assert_eq!(season.percentile(Section::Batting, "max_ev"), Some(82));
+        assert_eq!(season.percentile(Section::Batting, "swing_length"), Some(55));
+        let batting = season.section(Section::Batting).into_iter().map(|(label, _)| label).collect::<Vec<_>>();
+        assert_eq!(&batting[..4], ["xwOBA", "xBA", "xSLG", "xISO"]);
+        assert_eq!(season.section(Section::Baserunning), [("Sprint Speed", Some(89))]);
+    }
+
+    #[test]
+    fn parses_the_pitching_table_into_pitcher_rankings() {
+        let player = parse_fixture(include_str!("../fixtures/synthetic-savant-pitcher-675911.html"));
+        let season = player.latest().unwrap();
+        assert!(season.pitcher() && !season.hitter());
+        assert_eq!(season.percentile(Section::Pitching, "xera"), Some(88));
+        assert_eq!(season.percentile(Section::Pitching, "xba"), Some(84));
+        assert_eq!(season.percentile(Section::Pitching, "k_pct"), Some(100));
+        assert_eq!(season.percentile(Section::Pitching, "fb_velo"), Some(98));
+        assert_eq!(season.percentile(Section::Pitching, "cb_spin"), None);
+        assert_eq!(season.percentile(Section::Pitching, "extension"), Some(93));
+    }
+
+    #[test]
+    fn two_way_players_keep_both_tables_apart() {
+        let hitting = parse_percentile_table(include_str!("../fixtures/synthetic-savant-hitter-660670.html")).unwrap().unwrap();
+        let pitching = parse_percentile_table(include_str!("../fixtures/synthetic-savant-pitcher-675911.html")).unwrap().unwrap();
+        let player = merge_percentile_tables(vec![hitting, pitching]).unwrap();
+        let season = player.season(2023).unwrap();
+        assert!(season.hitter() && season.pitcher());
+        assert_eq!(season.percentile(Section::Batting, "xba"), Some(100));
+        assert_eq!(season.percentile(Section::Pitching, "xba"), Some(84));
+        assert_eq!(season.sections().into_iter().map(|(section, _)| section).collect::<Vec<_>>(), SECTIONS);
+    }
+
+    #[test]
+    fn locates_columns_by_header_text_and_shows_unknown_ones() {
+        let player = parse_fixture(include_str!("../fixtures/synthetic-savant-schema-drift.html"));
+        assert_eq!(player.name, "Ronald Acuña Jr.");
+        let season = player.season(2024).unwrap();
+        assert_eq!(season.percentile(Section::Batting, "hard_hit_pct"), Some(80));
+        assert_eq!(season.percentile(Section::Batting, "xwoba"), Some(64));
+        assert_eq!(season.percentile(Section::Batting, "bb_pct"), Some(88));
+        assert_eq!(player.unknown_headers, ["sweet spot%"]);
+        assert_eq!(season.percentile(Section::Batting, "sweet spot%"), Some(55));
+        assert!(season.section(Section::Batting).contains(&("Sweet Spot%", Some(55))));
+    }
+
+    #[test]
+    fn pages_without_percentiles_are_not_an_error() {
+        assert!(parse_percentile_table(include_str!("../fixtures/synthetic-savant-no-percentiles.html")).unwrap().is_none());
+    }
+
+    #[test]
+    fn malformed_tables_are_errors_instead_of_panics() {
+        let no_headers = r#"<div class="bio-player-name">Someone</div><table id="percentileRankings"><tbody><tr><td>2024</td></tr></tbody></table>"#;
+        assert_eq!(parse_percentile_table(no_headers).err(), Some(PercentileParseError::MissingHeaders));
+        let no_year = r#"<div class="bio-player-name">Someone</div><table id="percentileRankings"><thead><tr><th>xwOBA</th></tr></thead><tbody><tr><td>50</td></tr></tbody></table>"#;
+        assert_eq!(parse_percentile_table(no_year).err(), Some(PercentileParseError::MissingYearColumn));
+        let no_name = r#"<table id="percentileRankings"><thead><tr><th>Year</th></tr></thead></table>"#;
+        assert_eq!(parse_percentile_table(no_name).err(), Some(PercentileParseError::MissingPlayerName));
+    }
+
+    #[test]
+    fn trends_mark_the_change_from_the_season_before() {
+        let player = parse_fixture(include_str!("../fixtures/synthetic-savant-hitter-660670.html"));
+        let seasons = [player.season(2023).unwrap(), player.season(2024).unwrap()];
+        let trend = |section: Section, id: &str, label: &str| format_percentile_trend(label, &seasons.map(|season| season.percentile(section, id)));
+        assert_eq!(trend(Section::Batting, "xwoba", "xwOBA").unwrap(), "xwOBA        100   64▼");
+        assert_eq!(trend(Section::Batting, "bb_pct", "BB %").unwrap(), "BB %          84   88▲");
+        assert_eq!(trend(Section::Fielding, "oaa", "Range (OAA)").unwrap(), "Range (OAA)   24   50▲");
+        // bat speed was only tracked from 2024, so there's nothing to compare it to
+        assert_eq!(trend(Section::Batting, "bat_speed", "Bat Speed").unwrap(), "Bat Speed      -   78 ");
+        assert_eq!(trend(Section::Pitching, "xera", "xERA"), None);
+        // a missing season is skipped over rather than compared against
+        assert_eq!(format_percentile_trend("xwOBA", &[Some(80), None, Some(80), Some(90)]).unwrap(), "xwOBA         80    -   80   90▲");
+    }
+
+    #[test]
+    fn comparisons_line_both_bars_up_on_the_divider() {
+        let even = format_percentile_comparison("xwOBA", Some(64), Some(60));
+        let ahead = format_percentile_comparison("K %", Some(100), Some(20));
+        let behind = format_percentile_comparison("Whiff %", Some(5), Some(95));
+        assert_eq!(even, "\n` 64% [     ----------|---------      ]  60%   +4` xwOBA");
+        assert_eq!(ahead, "\n`100% [---------------|---            ]  20%  +80` << K %");
+        assert_eq!(behind, "\n`  5% [              -|-------------- ]  95%  -90` Whiff % >>");
+        let divider = |line: &str| line.find('|').unwrap();
+        assert!(divider(&even) == divider(&ahead) && divider(&ahead) == divider(&behind));
+        // a metric only one of the players has is left out
+        assert_eq!(format_percentile_comparison("Bat Speed", Some(78), None), "");
+        assert_eq!(format_percentile_comparison("Extension", None, Some(93)), "");
+    }
+
+    fn search_results() -> Value {
+        serde_json::json!([
+            { "id": "660670", "name": "Acuña Jr., Ronald", "pos": "RF", "name_display_club": "Braves", "firstYear": "2018", "lastYear": "2024" },
+            { "id": "682668", "name": "Acuña, Luisangel", "pos": "SS", "name_display_club": "Mets", "firstYear": 2024, "lastYear": 2024 },
+            { "id": "not a number", "name": "Acuña, Nobody" },
+        ])
+    }
+
+    #[test]
+    fn player_ids_skip_the_search() {
+        assert!(matches!(search_players(" 660670 "), Ok(PlayerSearch::Found(660670))));
+    }
+
+    #[test]
+    fn exact_names_are_found_in_either_order() {
+        assert!(matches!(parse_player_search("Luisangel Acuña", &search_results()), PlayerSearch::Found(682668)));
+        assert!(matches!(parse_player_search("acuña jr., ronald", &search_results()), PlayerSearch::Found(660670)));
+        assert!(matches!(parse_player_search("acuña", &serde_json::json!([])), PlayerSearch::NotFound));
+        let PlayerSearch::Candidates(candidates) = parse_player_search("acuña", &search_results()) else { panic!("expected both players") };
+        // results without a usable id are dropped
+        assert_eq!(candidates.iter().map(|candidate| candidate.id).collect::<Vec<_>>(), [660670, 682668]);
+    }
+
+    #[test]
+    fn candidates_are_summarized_for_the_menu() {
+        let PlayerSearch::Candidates(candidates) = parse_player_search("acuña", &search_results()) else { panic!("expected both players") };
+        assert_eq!(candidates[0].summary(), "RF · Braves · 2018-2024");
+        assert_eq!(candidates[1].summary(), "SS · Mets · 2024");
+        let unknown = PlayerCandidate { id: 1, name: "Someone".to_owned(), position: None, team: None, first_year: None, last_year: Some(2019) };
+        assert_eq!(unknown.summary(), "2019");
+        assert_eq!(PlayerCandidate { last_year: None, ..unknown }.summary(), "");
+    }
+
+    #[test]
+    fn stat_values_are_formatted_like_savant_shows_them() {
+        assert_eq!(ValueFormat::Rate.format(0.4123), ".412");
+        assert_eq!(ValueFormat::Decimal(" mph").format(94.06), "94.1 mph");
+        assert_eq!(ValueFormat::Percent.format(17.26), "17.3%");
+        assert_eq!(ValueFormat::Integer.format(2514.4), "2514");
+        assert!(format_percentile_ranking("xwOBA", Some(99), Some(".412")).ends_with("***xwOBA*** .412"));
+    }
+}
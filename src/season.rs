@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local};
+
+use crate::config::config;
+use crate::{get_fresh_with_sleep, get_with_sleep};
+use crate::magic::{Record, SeasonSeries};
+
+pub const AL_LEAGUE_ID: i64 = 103;
+pub const NL_LEAGUE_ID: i64 = 104;
+
+pub struct TeamRecord {
+    pub id: i64,
+    pub name: String,
+    pub location_name: String,
+    pub abbreviation: String,
+    pub league_id: i64,
+    pub division_id: i64,
+    pub division_name: String,
+    pub wins: u32,
+    pub losses: u32,
+}
+
+impl TeamRecord {
+    pub fn games_played(&self) -> u32 {
+        self.wins + self.losses
+    }
+
+    pub fn matches(&self, query: &str) -> bool {
+        [&self.name, &self.location_name, &self.abbreviation, &format!("{} {}", self.location_name, self.name)].into_iter().any(|name| name.eq_ignore_ascii_case(query.trim()))
+    }
+
+    pub fn record(&self) -> Record {
+        Record { wins: self.wins, losses: self.losses }
+    }
+}
+
+pub struct ScheduledGame {
+    pub home_id: i64,
+    pub away_id: i64,
+    pub winner_id: Option<i64>,
+}
+
+impl ScheduledGame {
+    pub fn is_between(&self, a: i64, b: i64) -> bool {
+        (self.home_id == a && self.away_id == b) || (self.home_id == b && self.away_id == a)
+    }
+}
+
+pub fn get_team_records() -> Result<Vec<TeamRecord>> {
+    let standings = get_with_sleep(&format!("{}/api/v1/standings?leagueId=103,104&season={year}&standingsTypes=regularSeason&hydrate=team,division", config().statsapi_url, year = Local::now().date_naive().year()))?;
+    let mut teams = Vec::new();
+    for division in standings["records"].as_array().context("Could not get standings")? {
+        let division_id = division["division"]["id"].as_i64().context("Could not get division id")?;
+        let division_name = division["division"]["nameShort"].as_str().context("Could not get division name")?;
+        let league_id = division["league"]["id"].as_i64().context("Could not get league id")?;
+        for team in division["teamRecords"].as_array().context("Could not get divisions teams")? {
+            teams.push(TeamRecord {
+                id: team["team"]["id"].as_i64().context("Could not get team id")?,
+                name: team["team"]["clubName"].as_str().context("Could not get club name")?.to_owned(),
+                location_name: team["team"]["locationName"].as_str().context("Could not get location name")?.to_owned(),
+                abbreviation: team["team"]["abbreviation"].as_str().context("Could not get abbreviation")?.to_owned(),
+                league_id,
+                division_id,
+                division_name: division_name.to_owned(),
+                wins: team["wins"].as_u64().context("Could not get wins")? as u32,
+                losses: team["losses"].as_u64().context("Could not get losses")? as u32,
+            });
+        }
+    }
+    Ok(teams)
+}
+
+// from a fresh schedule, games that finished since a cached copy would be simulated again on top of the standings
+pub fn get_season_games() -> Result<Vec<ScheduledGame>> {
+    let schedule = get_fresh_with_sleep(&format!("{}/api/v1/schedule?sportId=1&gameType=R&startDate={year}-01-01&endDate={year}-12-31", config().statsapi_url, year = Local::now().date_naive().year()))?;
+    Ok(schedule["dates"]
+        .as_array()
+        .context("Could not get schedule dates")?
+        .iter()
+        .flat_map(|date| date["games"].as_array().into_iter().flatten())
+        .filter(|game| !matches!(game["status"]["detailedState"].as_str(), Some("Postponed" | "Cancelled")))
+        .filter_map(|game| {
+            let home_id = game["teams"]["home"]["team"]["id"].as_i64()?;
+            let away_id = game["teams"]["away"]["team"]["id"].as_i64()?;
+            let winner_id = if game["status"]["abstractGameState"].as_str() != Some("Final") { None } else if game["teams"]["home"]["isWinner"].as_bool() == Some(true) { Some(home_id) } else if game["teams"]["away"]["isWinner"].as_bool() == Some(true) { Some(away_id) } else { None };
+            Some(ScheduledGame { home_id, away_id, winner_id })
+        })
+        .collect())
+}
+
+pub fn get_remaining_games() -> Result<Vec<ScheduledGame>> {
+    Ok(get_season_games()?.into_iter().filter(|game| game.winner_id.is_none()).collect())
+}
+
+pub fn get_season_series(games: &[ScheduledGame], team_id: i64, rival_id: i64) -> SeasonSeries {
+    let games = games.iter().filter(|game| game.is_between(team_id, rival_id)).collect::<Vec<_>>();
+    SeasonSeries {
+        wins: games.iter().filter(|game| game.winner_id == Some(team_id)).count() as u32,
+        losses: games.iter().filter(|game| game.winner_id == Some(rival_id)).count() as u32,
+        games: games.len() as u32,
+    }
+}
+
+// league and division ids of the configured team
+pub fn get_home_division() -> Result<(i64, i64)> {
+    let teams = get_with_sleep(&format!("{}/api/v1/teams/{}", config().statsapi_url, config().team_id))?;
+    let team = teams["teams"].as_array().and_then(|teams| teams.first()).context("Could not find the configured team")?;
+    Ok((team["league"]["id"].as_i64().context("Could not get the team's league id")?, team["division"]["id"].as_i64().context("Could not get the team's division id")?))
+}
+
+// league and division ids for the `league` and `division` arguments, `home`'s league and division by default
+pub fn standings_target(league: Option<&str>, division: Option<&str>, (home_league_id, home_division_id): (i64, i64)) -> (i64, i64) {
+    let american_league = match league {
+        Some(league) => league == "al",
+        None => home_league_id == AL_LEAGUE_ID,
+    };
+    // division ids run al west, east, central then nl west, east, central from 200
+    let division = match division {
+        Some("west") => 0,
+        Some("east") => 1,
+        Some("central") => 2,
+        _ => (home_division_id - 200).rem_euclid(3),
+    };
+
+    (if american_league { AL_LEAGUE_ID } else { NL_LEAGUE_ID }, 200 + division + (!american_league) as i64 * 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standings_default_to_the_home_division() {
+        let (nl_east, al_central) = ((NL_LEAGUE_ID, 204), (AL_LEAGUE_ID, 202));
+        assert_eq!(standings_target(None, None, nl_east), (NL_LEAGUE_ID, 204));
+        assert_eq!(standings_target(None, None, al_central), (AL_LEAGUE_ID, 202));
+        assert_eq!(standings_target(Some("al"), None, nl_east), (AL_LEAGUE_ID, 201));
+        assert_eq!(standings_target(None, Some("west"), al_central), (AL_LEAGUE_ID, 200));
+        assert_eq!(standings_target(Some("nl"), Some("central"), al_central), (NL_LEAGUE_ID, 205));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const STORAGE_PATH: &str = "bunt.json";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct GuildConfig {
+    // normalized alias -> player id
+    pub aliases: HashMap<String, usize>,
+    // replaces the configured prefix in this guild
+    pub prefix: Option<String>,
+}
+
+// something already announced, so it isn't posted twice after a restart
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PostedEvent {
+    pub key: String,
+    pub posted_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    pub version: usize,
+    pub current_game_id: Option<usize>,
+    pub guilds: HashMap<u64, GuildConfig>,
+    pub posted: Vec<PostedEvent>,
+}
+
+type Migration = fn(&mut Value) -> Result<()>;
+
+// each migration upgrades the stored document by one version, new ones are only ever appended
+const MIGRATIONS: &[Migration] = &[create_tables];
+
+// guilds are `{ "aliases": { normalized alias: player id }, "prefix": string or null }`
+fn create_tables(state: &mut Value) -> Result<()> {
+    let state = state.as_object_mut().context("Could not read storage as an object")?;
+    for (table, empty) in [("current_game_id", Value::Null), ("guilds", Value::Object(Default::default())), ("posted", Value::Array(vec![]))] {
+        state.entry(table).or_insert(empty);
+    }
+    for guild in state["guilds"].as_object_mut().context("Could not get guilds")?.values_mut() {
+        guild.as_object_mut().context("Could not read a guild as an object")?.entry("aliases").or_insert(Value::Object(Default::default()));
+    }
+    Ok(())
+}
+
+impl State {
+    // records `key` as posted, false when it already was
+    pub fn mark_posted(&mut self, key: String) -> bool {
+        if self.posted.iter().any(|event| event.key == key) {
+            return false
+        }
+        self.posted.push(PostedEvent { key, posted_at: Utc::now() });
+        true
+    }
+}
+
+pub struct Storage {
+    path: PathBuf,
+    state: State,
+}
+
+impl Storage {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Storage> {
+        let path = path.into();
+        let mut document = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice::<Value>(&bytes).with_context(|| format!("Could not parse {}", path.display()))?,
+            // anything else could be existing guild data that a fresh store would overwrite
+            Err(e) if e.kind() == ErrorKind::NotFound => serde_json::json!({ "version": 0 }),
+            Err(e) => bail!("Could not read {}: {e}", path.display()),
+        };
+        let version = document["version"].as_u64().context("Could not get storage version")? as usize;
+        if version > MIGRATIONS.len() {
+            bail!("{} was written by a newer version of bunt (schema {version})", path.display());
+        }
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            migration(&mut document).with_context(|| format!("Could not migrate storage to schema {}", version + 1))?;
+            document["version"] = (version + 1).into();
+        }
+        let mut storage = Storage { path, state: serde_json::from_value(document).context("Could not read storage")? };
+        storage.save()?;
+        Ok(storage)
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    // changes are written out straight away
+    pub fn update<T>(&mut self, change: impl FnOnce(&mut State) -> T) -> Result<T> {
+        let result = change(&mut self.state);
+        self.save()?;
+        Ok(result)
+    }
+
+    fn save(&mut self) -> Result<()> {
+        // a crash mid-write only ever loses the temporary file
+        let temporary = self.path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(&self.state)?).with_context(|| format!("Could not write {}", temporary.display()))?;
+        std::fs::rename(&temporary, &self.path).with_context(|| format!("Could not replace {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("bunt-storage-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn a_new_store_is_created_at_the_latest_schema() {
+        let directory = directory("new");
+        let storage = Storage::load(directory.join(STORAGE_PATH)).unwrap();
+        assert_eq!(storage.state().version, MIGRATIONS.len());
+        assert!(storage.state().guilds.is_empty() && storage.state().current_game_id.is_none());
+        assert!(directory.join(STORAGE_PATH).exists());
+    }
+
+    #[test]
+    fn changes_survive_a_restart() {
+        let directory = directory("restart");
+        let mut storage = Storage::load(directory.join(STORAGE_PATH)).unwrap();
+        storage.update(|state| {
+            state.current_game_id = Some(745804);
+            state.guilds.entry(123).or_default().aliases.insert("ronnie".to_owned(), 660670);
+        }).unwrap();
+
+        let storage = Storage::load(directory.join(STORAGE_PATH)).unwrap();
+        assert_eq!(storage.state().current_game_id, Some(745804));
+        assert_eq!(storage.state().guilds[&123].aliases["ronnie"], 660670);
+    }
+
+    #[test]
+    fn posted_events_are_only_posted_once() {
+        let directory = directory("posted");
+        let mut storage = Storage::load(directory.join(STORAGE_PATH)).unwrap();
+        assert!(storage.update(|state| state.mark_posted("percentile-header:sweet spot%".to_owned())).unwrap());
+        assert!(!storage.update(|state| state.mark_posted("percentile-header:sweet spot%".to_owned())).unwrap());
+
+        let mut storage = Storage::load(directory.join(STORAGE_PATH)).unwrap();
+        assert!(!storage.update(|state| state.mark_posted("percentile-header:sweet spot%".to_owned())).unwrap());
+        assert!(storage.update(|state| state.mark_posted("percentile-header:bat speed".to_owned())).unwrap());
+    }
+
+    #[test]
+    fn unreadable_stores_are_refused_instead_of_replaced() {
+        let directory = directory("unreadable");
+        std::fs::create_dir(directory.join(STORAGE_PATH)).unwrap();
+        assert!(Storage::load(directory.join(STORAGE_PATH)).is_err());
+        assert!(directory.join(STORAGE_PATH).is_dir());
+    }
+
+    #[test]
+    fn newer_schemas_are_refused() {
+        let directory = directory("newer");
+        std::fs::write(directory.join(STORAGE_PATH), r#"{"version": 999}"#).unwrap();
+        assert!(Storage::load(directory.join(STORAGE_PATH)).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::pitch::get_game_pitches;
+
+pub struct WpaPlay {
+    pub at_bat: u64,
+    pub inning: Option<u64>,
+    pub home_batting: Option<bool>,
+    pub batter_name: Option<String>,
+    pub pitcher_name: Option<String>,
+    pub description: Option<String>,
+    // percentage points, after the play
+    pub home_win_probability: f64,
+    pub home_added: f64,
+}
+
+impl WpaPlay {
+    pub fn summary(&self) -> String {
+        let inning = self.inning.map(|inning| format!("Inning {inning}: ")).unwrap_or_default();
+        let description = self.description.clone().or_else(|| self.batter_name.clone()).unwrap_or_else(|| format!("Plate appearance {}", self.at_bat));
+        format!("{inning}{description}")
+    }
+}
+
+// every entry of the gf feed's win probability table, joined with the plate appearance it belongs to
+pub fn get_game_wpa(game: &Value) -> Result<Vec<WpaPlay>> {
+    let home_id = game["scoreboard"]["teams"]["home"]["id"].as_i64();
+    let pitches = get_game_pitches(game);
+    let mut plays = Vec::new();
+    for wpa in game["scoreboard"]["stats"]["wpa"]["gameWpa"].as_array().context("Could not get WPA table")? {
+        let at_bat = wpa["atBatIndex"].as_u64().context("Could not get at bat index")? + 1;
+        let last_pitch = pitches.iter().rfind(|pitch| pitch.at_bat == at_bat);
+        plays.push(WpaPlay {
+            at_bat,
+            inning: last_pitch.and_then(|pitch| pitch.inning),
+            home_batting: last_pitch.and_then(|pitch| Some(pitch.batting_team_id? == home_id?)),
+            batter_name: last_pitch.map(|pitch| pitch.batter_name.clone()),
+            pitcher_name: last_pitch.map(|pitch| pitch.pitcher_name.clone()),
+            description: last_pitch.and_then(|pitch| pitch.result.clone()),
+            home_win_probability: wpa["homeTeamWinProbability"].as_f64().context("Could not get win probability")?,
+            home_added: wpa["homeTeamWinProbabilityAdded"].as_f64().context("Could not get WPA")?,
+        });
+    }
+    Ok(plays)
+}
+
+// indices of the plays that moved the win probability most, biggest first
+pub fn biggest_swings(plays: &[WpaPlay], count: usize) -> Vec<usize> {
+    let mut indices = (0..plays.len()).collect::<Vec<_>>();
+    indices.sort_by(|a, b| plays[*b].home_added.abs().total_cmp(&plays[*a].home_added.abs()));
+    indices.truncate(count);
+    indices
+}
+
+pub struct PlayerWpa {
+    pub name: String,
+    pub home: bool,
+    pub wpa: f64,
+}
+
+// the batter is credited with the play's WPA for their team and the pitcher with the same for theirs, highest first
+pub fn player_wpa(plays: &[WpaPlay]) -> Vec<PlayerWpa> {
+    let mut players = Vec::<PlayerWpa>::new();
+    for play in plays {
+        let Some(home_batting) = play.home_batting else { continue };
+        let batting_wpa = if home_batting { play.home_added } else { -play.home_added };
+        for (name, home, wpa) in [(&play.batter_name, home_batting, batting_wpa), (&play.pitcher_name, !home_batting, -batting_wpa)] {
+            let Some(name) = name else { continue };
+            match players.iter_mut().find(|player| player.name == *name && player.home == home) {
+                Some(player) => player.wpa += wpa,
+                None => players.push(PlayerWpa { name: name.clone(), home, wpa }),
+            }
+        }
+    }
+    players.sort_by(|a, b| b.wpa.total_cmp(&a.wpa));
+    players
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Value {
+        serde_json::json!({
+            "scoreboard": {
+                "teams": { "home": { "id": 144 } },
+                "stats": { "wpa": { "gameWpa": [
+                    { "atBatIndex": 0, "homeTeamWinProbability": 47.5, "homeTeamWinProbabilityAdded": -2.5 },
+                    { "atBatIndex": 1, "homeTeamWinProbability": 61.0, "homeTeamWinProbabilityAdded": 13.5 },
+                    { "atBatIndex": 2, "homeTeamWinProbability": 44.0, "homeTeamWinProbabilityAdded": -17.0 },
+                ] } },
+            },
+            "team_home": [
+                { "game_total_pitches": 2, "ab_number": 2, "inning": 1, "team_batting_id": 144, "batter_name": "Ronald Acuña Jr.", "pitcher_name": "Zack Wheeler", "des": "Ronald Acuña Jr. homers (12) on a fly ball to left field." },
+            ],
+            "team_away": [
+                { "game_total_pitches": 1, "ab_number": 1, "inning": 1, "team_batting_id": 143, "batter_name": "Kyle Schwarber", "pitcher_name": "Spencer Strider", "des": "Kyle Schwarber strikes out swinging." },
+            ],
+        })
+    }
+
+    #[test]
+    fn plays_are_joined_with_their_plate_appearance() {
+        let plays = get_game_wpa(&game()).unwrap();
+        assert_eq!(plays.len(), 3);
+        assert_eq!(plays[1].home_batting, Some(true));
+        assert_eq!(plays[0].home_batting, Some(false));
+        assert_eq!(plays[1].summary(), "Inning 1: Ronald Acuña Jr. homers (12) on a fly ball to left field.");
+        // the third plate appearance has no pitches in the feed yet
+        assert_eq!(plays[2].summary(), "Plate appearance 3");
+    }
+
+    #[test]
+    fn swings_are_ranked_by_size_either_way() {
+        let plays = get_game_wpa(&game()).unwrap();
+        assert_eq!(biggest_swings(&plays, 2), [2, 1]);
+        assert_eq!(biggest_swings(&plays, 5).len(), 3);
+    }
+
+    #[test]
+    fn batters_and_pitchers_split_each_play() {
+        let players = player_wpa(&get_game_wpa(&game()).unwrap());
+        let wpa = |name: &str| players.iter().find(|player| player.name == name).map(|player| (player.home, player.wpa));
+        assert_eq!(wpa("Ronald Acuña Jr."), Some((true, 13.5)));
+        assert_eq!(wpa("Zack Wheeler"), Some((false, -13.5)));
+        assert_eq!(wpa("Kyle Schwarber"), Some((false, 2.5)));
+        assert_eq!(wpa("Spencer Strider"), Some((true, -2.5)));
+        assert_eq!(players[0].name, "Ronald Acuña Jr.");
+        assert_eq!(players.len(), 4);
+    }
+}